@@ -0,0 +1,380 @@
+use {
+    gettextrs::gettext,
+    image::{
+        codecs::jpeg::JpegEncoder, imageops::FilterType, metadata::Orientation, DynamicImage,
+        ImageDecoder, ImageFormat, ImageReader,
+    },
+    std::{
+        fmt,
+        fs::{self, File},
+        io::{BufWriter, Cursor},
+        path::Path,
+    },
+};
+
+/// The quality used when a JPEG has to be encoded again
+const JPEG_QUALITY: u8 = 90;
+
+/// The PNG file signature
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+#[derive(Debug, Default)]
+/// What was done to an image while copying it
+pub struct Report {
+    /// The kinds of metadata which were removed
+    pub stripped: Vec<&'static str>,
+    /// The original and new dimensions, if the image was downscaled
+    pub resized: Option<((u32, u32), (u32, u32))>,
+}
+
+impl Report {
+    /// Returns true if the image was copied unchanged
+    pub fn is_empty(&self) -> bool {
+        self.stripped.is_empty() && self.resized.is_none()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.stripped.is_empty() {
+            write!(f, "{} {}", gettext("removed"), self.stripped.join(", "))?;
+        }
+        if let Some(((w, h), (nw, nh))) = self.resized {
+            if !self.stripped.is_empty() {
+                write!(f, "; ")?;
+            }
+            write!(f, "{} {w}x{h} -> {nw}x{nh}", gettext("resized"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Copies a file from the content directory into the generated capsule. JPEG
+/// and PNG images have their metadata removed and may be downscaled, depending
+/// on the `images` settings in `Config.ron`. Returns a report of the changes
+/// made to an image, or `None` for any other file. The original file is never
+/// modified.
+/// # Errors
+/// Returns an error if the file cannot be read or written, or if an image
+/// which has to be encoded again cannot be decoded
+pub fn copy(src: &Path, dest: &Path) -> Result<Option<Report>, crate::Error> {
+    let cfg = &crate::load_config().images;
+    let format = match src.extension().and_then(|e| e.to_str()) {
+        Some(e) if e.eq_ignore_ascii_case("jpg") || e.eq_ignore_ascii_case("jpeg") => {
+            ImageFormat::Jpeg
+        }
+        Some(e) if e.eq_ignore_ascii_case("png") => ImageFormat::Png,
+        _ => {
+            fs::copy(src, dest)?;
+            return Ok(None);
+        }
+    };
+    if !cfg.strip && cfg.max_size.is_none() {
+        fs::copy(src, dest)?;
+        return Ok(None);
+    }
+    let data = fs::read(src)?;
+    let mut report = Report::default();
+    let stripped = match format {
+        ImageFormat::Jpeg => strip_jpeg(&data, &mut report.stripped),
+        _ => strip_png(&data, &mut report.stripped),
+    };
+    let Some(stripped) = stripped.filter(|_| cfg.strip) else {
+        // Not a valid image, or stripping is disabled
+        report.stripped.clear();
+        return resize(src, dest, &data, format, report);
+    };
+    let Ok(mut decoder) = ImageReader::with_format(Cursor::new(&data), format).into_decoder()
+    else {
+        fs::write(dest, stripped)?;
+        return Ok(Some(report));
+    };
+    let (w, h) = decoder.dimensions();
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+    let too_big = cfg.max_size.is_some_and(|max| w.max(h) > max);
+    if too_big || orientation != Orientation::NoTransforms {
+        // The orientation is stored in the metadata being removed, so the image
+        // has to be rotated before it is saved
+        return resize(src, dest, &data, format, report);
+    }
+    fs::write(dest, stripped)?;
+    Ok(Some(report))
+}
+
+/// Decodes the image, applies it's orientation and downscales it if it is
+/// larger than the configured maximum, then encodes it again. Encoding the
+/// image again leaves no metadata behind.
+fn resize(
+    src: &Path,
+    dest: &Path,
+    data: &[u8],
+    format: ImageFormat,
+    mut report: Report,
+) -> Result<Option<Report>, crate::Error> {
+    let cfg = &crate::load_config().images;
+    let Ok(mut decoder) = ImageReader::with_format(Cursor::new(data), format).into_decoder() else {
+        fs::copy(src, dest)?;
+        return Ok(None);
+    };
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+    let mut img = DynamicImage::from_decoder(decoder)?;
+    img.apply_orientation(orientation);
+    let (w, h) = (img.width(), img.height());
+    if let Some(max) = cfg.max_size.filter(|max| w.max(h) > *max) {
+        img = img.resize(max, max, FilterType::Lanczos3);
+        report.resized = Some(((w, h), (img.width(), img.height())));
+    } else if !cfg.strip {
+        fs::copy(src, dest)?;
+        return Ok(Some(report));
+    }
+    let mut writer = BufWriter::new(File::create(dest)?);
+    match format {
+        ImageFormat::Jpeg => {
+            img.write_with_encoder(JpegEncoder::new_with_quality(&mut writer, JPEG_QUALITY))?;
+        }
+        _ => img.write_to(&mut writer, format)?,
+    }
+    Ok(Some(report))
+}
+
+/// Removes the EXIF, XMP, IPTC and comment segments from a JPEG, keeping the
+/// color profile. Anything following the end of the image, such as the extra
+/// images of a multi-picture file with their own metadata, is dropped along
+/// with the MPF segment indexing them. Returns `None` if the data is not a
+/// valid JPEG, in which case the file is copied unchanged.
+fn strip_jpeg(data: &[u8], stripped: &mut Vec<&'static str>) -> Option<Vec<u8>> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+    let mut out = vec![0xFF, 0xD8];
+    let mut pos = 2;
+    loop {
+        if *data.get(pos)? != 0xFF {
+            return None;
+        }
+        let marker = *data.get(pos + 1)?;
+        // Fill bytes which may pad a marker
+        if marker == 0xFF {
+            pos += 1;
+            continue;
+        }
+        if marker == 0xD9 {
+            out.extend_from_slice(&[0xFF, 0xD9]);
+            if pos + 2 < data.len() && !stripped.contains(&"trailing data") {
+                stripped.push("trailing data");
+            }
+            return Some(out);
+        }
+        let len = usize::from(u16::from_be_bytes([
+            *data.get(pos + 2)?,
+            *data.get(pos + 3)?,
+        ]));
+        if len < 2 {
+            return None;
+        }
+        let segment = data.get(pos..pos + 2 + len)?;
+        let payload = &segment[4..];
+        let kind = match marker {
+            0xE1 if payload.starts_with(b"Exif\0") => Some("EXIF"),
+            0xE1 if payload.starts_with(b"http://ns.adobe.com/xap/") => Some("XMP"),
+            0xE1 => Some("APP1"),
+            0xE2 if payload.starts_with(b"MPF\0") => Some("MPF"),
+            0xED => Some("IPTC"),
+            0xFE => Some("comment"),
+            _ => None,
+        };
+        match kind {
+            Some(k) => {
+                if !stripped.contains(&k) {
+                    stripped.push(k);
+                }
+            }
+            None => out.extend_from_slice(segment),
+        }
+        pos += 2 + len;
+        // Start of scan, after which the compressed image data follows
+        if marker == 0xDA {
+            let end = scan_end(data, pos);
+            out.extend_from_slice(&data[pos..end]);
+            if end == data.len() {
+                // Truncated, without an end of image marker
+                return Some(out);
+            }
+            pos = end;
+        }
+    }
+}
+
+/// Returns the position of the first marker after the compressed image data
+/// starting at `pos`, or the end of the data if there is none. Within the
+/// image data a `0xFF` byte is followed by a zero byte or a restart marker.
+fn scan_end(data: &[u8], pos: usize) -> usize {
+    let mut i = pos;
+    while i + 1 < data.len() {
+        if data[i] == 0xFF && !matches!(data[i + 1], 0x00 | 0xD0..=0xD7) {
+            return i;
+        }
+        i += 1;
+    }
+    data.len()
+}
+
+/// Removes the EXIF, XMP, text and timestamp chunks from a PNG. Returns `None`
+/// if the data is not a valid PNG, in which case the file is copied unchanged.
+fn strip_png(data: &[u8], stripped: &mut Vec<&'static str>) -> Option<Vec<u8>> {
+    if !data.starts_with(PNG_SIGNATURE) {
+        return None;
+    }
+    let mut out = PNG_SIGNATURE.to_vec();
+    let mut pos = PNG_SIGNATURE.len();
+    while pos < data.len() {
+        let len = u32::from_be_bytes(data.get(pos..pos + 4)?.try_into().ok()?);
+        let len = usize::try_from(len).ok()?;
+        // Length, type, data and crc
+        let chunk = data.get(pos..pos + 12 + len)?;
+        let payload = &chunk[8..8 + len];
+        let kind = match &chunk[4..8] {
+            b"eXIf" => Some("EXIF"),
+            b"iTXt" if payload.starts_with(b"XML:com.adobe.xmp\0") => Some("XMP"),
+            b"tEXt" | b"zTXt" | b"iTXt" => Some("text"),
+            b"tIME" => Some("timestamp"),
+            _ => None,
+        };
+        match kind {
+            Some(k) => {
+                if !stripped.contains(&k) {
+                    stripped.push(k);
+                }
+            }
+            None => out.extend_from_slice(chunk),
+        }
+        pos += chunk.len();
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a JPEG marker segment with the given payload
+    fn segment(marker: u8, payload: &[u8]) -> Vec<u8> {
+        let len = u16::try_from(payload.len() + 2).unwrap();
+        let mut seg = vec![0xFF, marker];
+        seg.extend_from_slice(&len.to_be_bytes());
+        seg.extend_from_slice(payload);
+        seg
+    }
+
+    /// Returns a JPEG made of the given segments, one scan of compressed data
+    /// containing stuffed bytes and a restart marker, and the end marker
+    fn jpeg(segments: &[Vec<u8>]) -> Vec<u8> {
+        let mut data = vec![0xFF, 0xD8];
+        for seg in segments {
+            data.extend_from_slice(seg);
+        }
+        data.extend(segment(0xDA, &[1, 1, 0, 0, 63, 0]));
+        data.extend_from_slice(&[0x12, 0xFF, 0x00, 0x34, 0xFF, 0xD0, 0x56]);
+        data.extend_from_slice(&[0xFF, 0xD9]);
+        data
+    }
+
+    /// Returns a PNG chunk, with a dummy crc
+    fn chunk(kind: [u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut chunk = u32::try_from(payload.len()).unwrap().to_be_bytes().to_vec();
+        chunk.extend_from_slice(&kind);
+        chunk.extend_from_slice(payload);
+        chunk.extend_from_slice(&[0; 4]);
+        chunk
+    }
+
+    #[test]
+    fn jpeg_exif_removed_icc_kept() {
+        let icc = segment(0xE2, b"ICC_PROFILE\0\x01\x01profile");
+        let dqt = segment(0xDB, &[0; 65]);
+        let data = jpeg(&[
+            segment(0xE1, b"Exif\0\0GPS"),
+            icc.clone(),
+            segment(0xFE, b"comment"),
+            dqt.clone(),
+        ]);
+        let mut stripped = vec![];
+        let out = strip_jpeg(&data, &mut stripped).unwrap();
+        assert_eq!(out, jpeg(&[icc, dqt]));
+        assert_eq!(stripped, ["EXIF", "comment"]);
+    }
+
+    #[test]
+    fn jpeg_multiple_scans_round_trip() {
+        // A progressive image has several scans, with tables between them
+        let mut data = vec![0xFF, 0xD8];
+        data.extend(segment(0xC2, &[8, 0, 1, 0, 1, 1, 1, 0x11, 0]));
+        for scan in 0..3 {
+            data.extend(segment(0xC4, &[0x10 + scan, 1, 0, 0, 0]));
+            data.extend(segment(0xDA, &[1, 1, 0, 0, 63, 0]));
+            data.extend_from_slice(&[0xAB, 0xFF, 0x00, 0xFF, 0xD7, 0xCD, scan]);
+        }
+        data.extend_from_slice(&[0xFF, 0xD9]);
+        let mut stripped = vec![];
+        assert_eq!(strip_jpeg(&data, &mut stripped).unwrap(), data);
+        assert!(stripped.is_empty());
+    }
+
+    #[test]
+    fn jpeg_trailing_data_dropped() {
+        let mpf = segment(0xE2, b"MPF\0index");
+        let main = jpeg(&[mpf, segment(0xDB, &[0; 65])]);
+        let second = jpeg(&[segment(0xE1, b"Exif\0\0GPS")]);
+        let mut data = main.clone();
+        data.extend_from_slice(&second);
+        let mut stripped = vec![];
+        let out = strip_jpeg(&data, &mut stripped).unwrap();
+        assert_eq!(out, jpeg(&[segment(0xDB, &[0; 65])]));
+        assert_eq!(stripped, ["MPF", "trailing data"]);
+    }
+
+    #[test]
+    fn png_metadata_removed() {
+        let ihdr = chunk(*b"IHDR", &[0, 0, 0, 1, 0, 0, 0, 1, 8, 0, 0, 0, 0]);
+        let plte = chunk(*b"PLTE", &[0, 0, 0]);
+        let idat = chunk(*b"IDAT", &[1, 2, 3]);
+        let iend = chunk(*b"IEND", &[]);
+        let mut data = PNG_SIGNATURE.to_vec();
+        for c in [
+            &ihdr,
+            &chunk(*b"eXIf", b"MM\0*GPS"),
+            &plte,
+            &chunk(*b"tEXt", b"Author\0me"),
+            &chunk(*b"tIME", &[7, 234, 10, 19, 0, 0, 0]),
+            &idat,
+            &iend,
+        ] {
+            data.extend_from_slice(c);
+        }
+        let mut stripped = vec![];
+        let out = strip_png(&data, &mut stripped).unwrap();
+        let mut expected = PNG_SIGNATURE.to_vec();
+        for c in [&ihdr, &plte, &idat, &iend] {
+            expected.extend_from_slice(c);
+        }
+        assert_eq!(out, expected);
+        assert_eq!(stripped, ["EXIF", "text", "timestamp"]);
+    }
+
+    #[test]
+    fn invalid_input_left_alone() {
+        let mut stripped = vec![];
+        assert!(strip_jpeg(b"not an image", &mut stripped).is_none());
+        assert!(strip_png(b"not an image", &mut stripped).is_none());
+        // Cut off inside a segment
+        let data = jpeg(&[segment(0xE1, b"Exif\0\0GPS")]);
+        assert!(strip_jpeg(&data[..8], &mut stripped).is_none());
+        let png = [PNG_SIGNATURE, &chunk(*b"IHDR", &[0; 13])[..10]].concat();
+        assert!(strip_png(&png, &mut stripped).is_none());
+        // Cut off inside the compressed data, which is kept as it is
+        let data = jpeg(&[segment(0xDB, &[0; 65])]);
+        let cut = &data[..data.len() - 3];
+        assert_eq!(strip_jpeg(cut, &mut stripped).unwrap(), cut);
+        assert!(stripped.is_empty());
+    }
+}
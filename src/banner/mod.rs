@@ -1,11 +1,106 @@
-use std::{fs, io, path::PathBuf};
-
-/// Returns the banner string if present
-pub fn get() -> Option<io::Result<String>> {
-    let path = PathBuf::from("banner.txt");
-    if path.exists() {
-        Some(fs::read_to_string(&path))
-    } else {
-        None
+use {
+    std::{
+        collections::HashMap,
+        fs, io,
+        path::{Path, PathBuf},
+    },
+    walkdir::WalkDir,
+};
+
+/// The name of the banner file, both in the capsule root and in any directory
+/// under `content`
+pub const FILE: &str = "banner.txt";
+
+#[derive(Clone, Debug, Default)]
+/// Every banner available to the capsule
+pub struct Banners {
+    /// The banner from `banner.txt` in the capsule root
+    root: Option<String>,
+    /// Banners which are picked per page
+    rotate: Vec<String>,
+    /// Banners from `banner.txt` files inside of the content directory, keyed
+    /// by their directory relative to `content`
+    dirs: HashMap<PathBuf, String>,
+}
+
+impl Banners {
+    /// Reads the root banner, the rotating banners and any per directory banners
+    /// # Errors
+    /// Returns an error if a banner file exists but cannot be read
+    pub fn load() -> io::Result<Self> {
+        let cfg = crate::load_config();
+        let path = PathBuf::from(FILE);
+        let root = if path.exists() {
+            Some(read(&path)?)
+        } else {
+            None
+        };
+        let mut rotate = vec![];
+        for file in &cfg.banner.rotate {
+            rotate.push(read(file)?);
+        }
+        let mut dirs = HashMap::new();
+        for entry in WalkDir::new("content").into_iter().flatten() {
+            if entry.file_type().is_file() && entry.file_name() == FILE {
+                let dir = entry
+                    .path()
+                    .parent()
+                    .and_then(|p| p.strip_prefix("content").ok())
+                    .map(Path::to_path_buf)
+                    .unwrap_or_default();
+                dirs.insert(dir, read(entry.path())?);
+            }
+        }
+        Ok(Self { root, rotate, dirs })
+    }
+
+    /// Returns the banner for the page at `path`, relative to `content`. The
+    /// closest `banner.txt` in the page's directory or it's parents is used if
+    /// there is one, followed by a banner picked from the rotating set and then
+    /// the root banner.
+    pub fn get(&self, path: &Path) -> Option<&str> {
+        let mut dir = path.parent();
+        while let Some(d) = dir {
+            if let Some(b) = self.dirs.get(d) {
+                return Some(b);
+            }
+            dir = d.parent();
+        }
+        if !self.rotate.is_empty() {
+            let idx = hash(&path.to_string_lossy()) % self.rotate.len() as u64;
+            return self
+                .rotate
+                .get(usize::try_from(idx).ok()?)
+                .map(String::as_str);
+        }
+        self.root.as_deref()
+    }
+}
+
+/// Reads a banner file, removing trailing whitespace
+fn read(path: &Path) -> io::Result<String> {
+    Ok(fs::read_to_string(path)?.trim_end().to_string())
+}
+
+/// A 64 bit FNV-1a hash, which unlike the standard library's hasher is stable
+/// between builds
+fn hash(s: &str) -> u64 {
+    s.bytes().fold(0xcbf2_9ce4_8422_2325, |h, b| {
+        (h ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Wraps the banner in a preformatted block
+pub fn block(banner: Option<&str>) -> String {
+    match banner {
+        Some(s) => {
+            let alt = crate::load_config()
+                .banner
+                .alt
+                .as_deref()
+                .unwrap_or_default();
+            format!("```{alt}\n{s}\n```\n")
+        }
+        None => String::new(),
     }
 }
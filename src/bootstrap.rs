@@ -13,9 +13,11 @@ fn docs(outdir: &str) -> Result<(), std::io::Error> {
     let docs = [
         "build.md",
         "customizing.md",
+        "deploy.md",
         "index.md",
         "page.md",
         "post.md",
+        "server.md",
         "tinylog.md",
     ];
     let outdir: PathBuf = [outdir, "share", "doc", "zond"].iter().collect();
@@ -91,6 +93,9 @@ fn main() -> Result<(), Box<dyn Error>> {
     Bootstrap::new("zond-page", cli::page(), &out).manpage(1)?;
     Bootstrap::new("zond-page-init", cli::page_init(), &out).manpage(1)?;
     Bootstrap::new("zond-tinylog", cli::tinylog(), &out).manpage(1)?;
+    Bootstrap::new("zond-serve", cli::serve(), &out).manpage(1)?;
+    Bootstrap::new("zond-deploy", cli::deploy(), &out).manpage(1)?;
+    Bootstrap::new("zond-server-config", cli::server_config(), &out).manpage(1)?;
     docs(&outdir)?;
     translations(&outdir)?;
     Ok(())
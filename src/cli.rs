@@ -109,6 +109,13 @@ pub fn build() -> Command {
                 .num_args(1)
                 .required(false),
         )
+        .arg(
+            Arg::new("watch")
+                .short('w')
+                .long("watch")
+                .help(gettext("Rebuild the capsule whenever it's content changes"))
+                .action(ArgAction::SetTrue),
+        )
 }
 
 #[must_use]
@@ -158,14 +165,28 @@ and included in feeds. Posts must be published before they will appear in the
 generated capsule, and will appear in reverse chronoogical order. Posts, like all
 pages, may also be categorized using tags, and a page will be auto generated for
 every tag in the capsule with links to every page and gemlog post which includes
-that tag.",
+that tag. Capsules with more than one collection of dated entries choose which
+one the post belongs to with `--collection`.",
         ))
         .visible_alias("po")
-        .arg(
+        .args([
             Arg::new("title")
                 .help(gettext("The title of the post"))
                 .num_args(1),
-        )
+            Arg::new("slug")
+                .help(gettext(
+                    "The file name of the post, if it should not be generated from the title",
+                ))
+                .long("slug")
+                .num_args(1),
+            Arg::new("collection")
+                .help(gettext(
+                    "The collection the post belongs to, defaulting to the gemlog",
+                ))
+                .short('c')
+                .long("collection")
+                .num_args(1),
+        ])
         .subcommands([
             post_init(),
             Command::new("publish")
@@ -240,6 +261,12 @@ section.",
                 .short('p')
                 .long("path")
                 .num_args(1),
+            Arg::new("slug")
+                .help(gettext(
+                    "The file name of the page, if it should not be generated from the title",
+                ))
+                .long("slug")
+                .num_args(1),
         ])
         .group(
             ArgGroup::new("specifier")
@@ -290,6 +317,97 @@ pub fn tinylog() -> Command {
         ])
 }
 
+#[must_use]
+/// The serve subcommand
+pub fn serve() -> Command {
+    Command::new("serve")
+        .about(gettext("Build and serve a preview of the capsule"))
+        .long_about(gettext(
+            "Builds the capsule into a temporary directory and serves it over \
+            Gemini on localhost, using a self signed certificate. Links to the \
+            capsule's domain are rewritten to point to the preview.",
+        ))
+        .visible_alias("s")
+        .arg(
+            Arg::new("port")
+                .short('p')
+                .long("port")
+                .help(gettext("The port to listen on"))
+                .value_parser(value_parser!(u16))
+                .default_value("1965")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("watch")
+                .short('w')
+                .long("watch")
+                .help(gettext("Rebuild the capsule whenever it's content changes"))
+                .action(ArgAction::SetTrue),
+        )
+}
+
+#[must_use]
+/// The deploy subcommand
+pub fn deploy() -> Command {
+    Command::new("deploy")
+        .about(gettext("Deploy the generated capsule"))
+        .long_about(gettext(
+            "Sends the generated capsule to one of the deploy targets set in \
+            Config.ron. Only files which have changed since the last deploy to \
+            that target are sent.",
+        ))
+        .visible_alias("dp")
+        .args([
+            Arg::new("target")
+                .help(gettext(
+                    "The name of the target, which may be left off if there is only one",
+                ))
+                .num_args(1),
+            Arg::new("output")
+                .short('o')
+                .long("output")
+                .help(gettext("The location of the generated capsule"))
+                .num_args(1)
+                .required(false),
+            Arg::new("dry-run")
+                .short('n')
+                .long("dry-run")
+                .help(gettext(
+                    "List the changes which would be made without making them",
+                ))
+                .action(ArgAction::SetTrue),
+        ])
+}
+
+#[must_use]
+/// The server-config subcommand
+pub fn server_config() -> Command {
+    Command::new("server-config")
+        .about(gettext("Generate configuration for a Gemini server"))
+        .long_about(gettext(
+            "Generates configuration for a Gemini server from Config.ron and the \
+            capsule's content. For Agate, a `.meta` file is written into each \
+            directory of the generated capsule, so the capsule must be built \
+            first. For other servers the configuration is printed to stdout.",
+        ))
+        .visible_alias("sc")
+        .args([
+            Arg::new("server")
+                .short('s')
+                .long("server")
+                .help(gettext("The server to generate configuration for"))
+                .value_parser(["agate", "molly-brown", "jetforce", "gmid"])
+                .num_args(1)
+                .required(true),
+            Arg::new("output")
+                .short('o')
+                .long("output")
+                .help(gettext("The location of the generated capsule"))
+                .num_args(1)
+                .required(false),
+        ])
+}
+
 #[must_use]
 /// Generates the command line options
 pub fn zond() -> Command {
@@ -299,5 +417,14 @@ pub fn zond() -> Command {
         .version(env!("CARGO_PKG_VERSION"))
         .subcommand_required(true)
         .arg_required_else_help(true)
-        .subcommands([init(), build(), post(), page(), tinylog()])
+        .subcommands([
+            init(),
+            build(),
+            post(),
+            page(),
+            tinylog(),
+            serve(),
+            deploy(),
+            server_config(),
+        ])
 }
@@ -1,44 +1,252 @@
 use {
     crate::{
-        content::{index::Index, Page, Time},
+        asset,
+        banner::{self, Banners},
+        config::{SortBy, GEMLOG},
+        content::{
+            annotate, index::Index, lang, permalink, section, stats, tag, toc, Context, Page,
+            Section, Time,
+        },
         link::Link,
         post::Post,
+        shortcode::{self, Expand, Shortcode},
+        strings::{self, TAGS_DIR},
+        template::{self, Template, Vars},
         GetPath, ToDisk,
     },
     atom_syndication::{self as atom, Feed},
     chrono::{Datelike, Utc},
     clap::ArgMatches,
     gettextrs::gettext,
+    notify::{RecursiveMode, Watcher},
     std::{
         borrow::Cow,
         cmp,
-        collections::{BTreeMap, HashMap},
+        collections::{BTreeMap, BTreeSet, HashMap},
         env,
-        fmt::Write,
+        fmt::{self, Write},
         fs::{self, File},
         io::{BufWriter, Write as IoWrite},
         path::{Path, PathBuf},
+        sync::mpsc,
+        time::{Duration, Instant},
     },
-    tinylog::Time as _,
+    tinylog::{Time as _, Tinylog},
     url::Url,
     walkdir::WalkDir,
 };
 
-/// A `BTreeMap` of gemlog posts
-type Posts = BTreeMap<i64, Post>;
-/// A `HashMap` of tag names and their associated links
-type Tags = HashMap<String, Vec<Link>>;
+/// A `BTreeMap` of posts, keyed by their publication time, collection and
+/// language so that translations of a post may share the same date
+type Posts = BTreeMap<(i64, String, Option<String>), Post>;
+
+/// A `BTreeMap` of tags, keyed by their slug
+type Tags = BTreeMap<String, Tagged>;
+
+#[derive(Default)]
+/// The pages carrying a tag, and the ways in which the tag was written
+struct Tagged {
+    /// Each spelling of the tag, with the number of pages using it
+    names: BTreeMap<String, usize>,
+    /// Links to the tagged pages
+    links: Vec<Link>,
+    /// The introduction written for the tag in `content/tags`, if any
+    intro: Option<Page>,
+}
+
+impl Tagged {
+    /// Returns the name the tag is displayed with. This is the title of it's
+    /// introduction if it has one, or else the spelling used by the most pages.
+    fn name(&self) -> &str {
+        if let Some(intro) = self.intro.as_ref().filter(|p| !p.meta.title.is_empty()) {
+            return &intro.meta.title;
+        }
+        self.names
+            .iter()
+            .rev()
+            .max_by_key(|(_, count)| **count)
+            .map_or("", |(name, _)| name.as_str())
+    }
+
+    /// Returns the last level of the tag's name, which is how it is shown
+    /// beneath it's parent
+    fn label(&self) -> &str {
+        let name = self.name();
+        if self.intro.is_some() {
+            return name;
+        }
+        name.rsplit(tag::SEPARATOR).next().unwrap_or(name)
+    }
+
+    /// Returns the summary from the tag's introduction
+    fn summary(&self) -> Option<&str> {
+        self.intro.as_ref()?.meta.summary.as_deref()
+    }
+}
+
+/// Attaches the introductions found in `content/tags` to their tags. Each is
+/// named after the tag's slug, such as `content/tags/os/linux.gmi`.
+fn load_intros(tags: &mut Tags) {
+    for (slug, tagged) in tags.iter_mut() {
+        let path = Path::new("content")
+            .join(TAGS_DIR)
+            .join(format!("{slug}.gmi"));
+        tagged.intro = Page::from_path(&path);
+    }
+}
+
+/// Records a page's tags. Each level above a hierarchical tag is added as a
+/// tag of it's own, even if no page carries it directly.
+fn add_tags(tags: &mut Tags, names: &[String], link: &Link) {
+    for name in names {
+        let name = tag::name(name);
+        let slug = tag::slug(&name);
+        if slug.is_empty() {
+            continue;
+        }
+        let t = tags.entry(slug.clone()).or_default();
+        if t.links.contains(link) {
+            continue;
+        }
+        *t.names.entry(name.clone()).or_default() += 1;
+        t.links.push(link.clone());
+        for (n, parent) in tag::parents(&slug).enumerate() {
+            let parent_name = name.split(tag::SEPARATOR).take(n + 1).collect::<Vec<_>>();
+            tags.entry(parent.to_string())
+                .or_default()
+                .names
+                .entry(parent_name.join("/"))
+                .or_default();
+        }
+    }
+}
 
 /// Performs the build
 /// # Errors
 /// Errors are bubbled up from the called functions
 pub fn run(matches: &ArgMatches) -> Result<(), crate::Error> {
+    let output = matches
+        .get_one::<String>("output")
+        .map_or("public", std::string::String::as_str);
+    build(Path::new(output))?;
+    if matches.get_flag("watch") {
+        watch(Path::new(output))?;
+    }
+    Ok(())
+}
+
+/// A short description of a completed build
+pub struct Summary {
+    /// The directory holding the capsule root. This differs from the requested
+    /// output directory if `path` is set in `Config.ron`.
+    pub output: PathBuf,
+    /// The number of published pages, including gemlog posts
+    pub pages: usize,
+    /// The number of gemlog posts
+    pub posts: usize,
+    /// The number of tags
+    pub tags: usize,
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {}, {} {}, {} {}",
+            self.pages,
+            gettext("pages"),
+            self.posts,
+            gettext("posts"),
+            self.tags,
+            gettext("tags")
+        )
+    }
+}
+
+/// Returns the relative url of the directory `to` from within the directory
+/// `from`, both of which are relative to the capsule root
+fn relative_dir(from: &Path, to: &Path) -> String {
+    let common = from
+        .components()
+        .zip(to.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut url = "../".repeat(from.components().count() - common);
+    for c in to.components().skip(common) {
+        url.push_str(&c.as_os_str().to_string_lossy());
+        url.push('/');
+    }
+    url
+}
+
+/// Returns an error if more than one page would be written to the same path,
+/// or if any of the pages' aliases collide
+fn check_paths(pages: &[Source], output: &Path) -> Result<(), crate::Error> {
+    let mut seen: HashMap<&Path, &Path> = HashMap::new();
+    for src in pages {
+        if let Some(other) = seen.insert(&src.output, &src.path) {
+            return Err(format!(
+                "{}: {} ({}, {})",
+                gettext("More than one page has the same permalink"),
+                src.link.url,
+                other.display(),
+                src.path.display()
+            )
+            .into());
+        }
+    }
+    check_aliases(pages, output)
+}
+
+/// Returns an error if an alias is the path of a published page, or if more
+/// than one page claims the same alias
+fn check_aliases(pages: &[Source], output: &Path) -> Result<(), crate::Error> {
+    let mut seen: HashMap<PathBuf, &Path> = HashMap::new();
+    for src in pages {
+        for alias in &src.page.meta.aliases {
+            let dest = output.join(permalink::alias(alias));
+            if let Some(page) = pages.iter().find(|p| p.output == dest) {
+                return Err(format!(
+                    "{}: {alias} ({}, {})",
+                    gettext("An alias is the path of a published page"),
+                    src.path.display(),
+                    page.path.display()
+                )
+                .into());
+            }
+            if let Some(other) = seen.insert(dest, &src.path) {
+                return Err(format!(
+                    "{}: {alias} ({}, {})",
+                    gettext("More than one page has the same alias"),
+                    other.display(),
+                    src.path.display()
+                )
+                .into());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the heading shown above a list of posts from `collection`
+fn heading(collection: &str) -> String {
+    let title = crate::load_config()
+        .collections()
+        .remove(collection)
+        .and_then(|c| c.title);
+    match title {
+        Some(t) => t,
+        None if collection == GEMLOG => strings::gettext("Gemlog posts"),
+        None => collection.to_string(),
+    }
+}
+
+/// Builds the capsule into `output`
+/// # Errors
+/// Errors are bubbled up from the called functions
+pub fn build(output: &Path) -> Result<Summary, crate::Error> {
     let cfg = crate::load_config();
-    let mut output = PathBuf::from(
-        matches
-            .get_one::<String>("output")
-            .map_or("public", std::string::String::as_str),
-    );
+    let mut output = output.to_path_buf();
     if let Some(ref path) = cfg.path {
         output.push(path);
     }
@@ -50,50 +258,268 @@ pub fn run(matches: &ArgMatches) -> Result<(), crate::Error> {
         fs::remove_dir_all(&output)?;
     }
     let capsule = Capsule::init(&output)?;
-    match cfg.feed {
-        Some(crate::config::Feed::Atom) => {
-            let atom = Feed::try_from(&capsule)?;
-            let dest = Feed::get_path(&output, None);
-            atom.to_disk(&dest)?;
+    capsule.write_pages()?;
+    for collection in cfg.collections().keys() {
+        for lang in capsule.languages() {
+            let lang = lang.as_deref();
+            if !capsule.has_posts(Some(collection), lang) && !lang::is_default(lang) {
+                continue;
+            }
+            match cfg.feed(collection) {
+                Some(crate::config::Feed::Atom) => {
+                    capsule.write_atom(&output, collection, lang)?;
+                }
+                Some(crate::config::Feed::Gemini) => {
+                    capsule.write_gemfeed(&output, collection, lang)?;
+                }
+                Some(crate::config::Feed::Both) => {
+                    capsule.write_atom(&output, collection, lang)?;
+                    capsule.write_gemfeed(&output, collection, lang)?;
+                }
+                None => {}
+            }
+            capsule.write_collection_index(&output, collection, lang)?;
         }
-        Some(crate::config::Feed::Gemini) => {
-            capsule.write_gemfeed(&output)?;
+    }
+    for lang in capsule.languages() {
+        capsule.write_index(&output, lang.as_deref())?;
+    }
+    capsule.write_tags(&output)?;
+    capsule.write_dir_indexes(&output)?;
+    if cfg.server.stubs {
+        capsule.write_stubs()?;
+    }
+    Ok(Summary {
+        output,
+        pages: capsule.pages.len(),
+        posts: capsule.posts.len(),
+        tags: capsule.tags.len(),
+    })
+}
+
+/// Copies a file from the content directory into the capsule, printing what was
+/// changed if image reports are enabled
+fn copy_asset(path: &Path, output: &Path, name: &Path) -> Result<(), crate::Error> {
+    let report = asset::copy(path, output)?;
+    if crate::load_config().images.report {
+        if let Some(r) = report.filter(|r| !r.is_empty()) {
+            println!("{}: {r}", name.display());
         }
-        Some(crate::config::Feed::Both) => {
-            let atom = Feed::try_from(&capsule)?;
-            let dest = Feed::get_path(&output, None);
-            atom.to_disk(&dest)?;
-            capsule.write_gemfeed(&output)?;
+    }
+    Ok(())
+}
+
+/// Returns true if a change to `path` should trigger a rebuild
+fn is_watched(path: &Path) -> bool {
+    let Ok(cwd) = env::current_dir() else {
+        return false;
+    };
+    let path = path
+        .strip_prefix(&cwd)
+        .or_else(|_| path.strip_prefix("."))
+        .unwrap_or(path);
+    path.starts_with("content")
+        || path.starts_with("templates")
+        || path == Path::new("Config.ron")
+        || path == Path::new(banner::FILE)
+        || crate::load_config().banner.rotate.iter().any(|b| b == path)
+}
+
+/// Watches the directories holding the rotating banners, where they are not
+/// already covered by the capsule root, content or templates
+fn watch_banners(watcher: &mut impl Watcher) -> Result<(), crate::Error> {
+    for file in &crate::load_config().banner.rotate {
+        let Some(dir) = file.parent().filter(|d| !d.as_os_str().is_empty()) else {
+            continue;
+        };
+        if dir.is_dir() && !dir.starts_with("content") && !dir.starts_with("templates") {
+            watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        }
+    }
+    Ok(())
+}
+
+/// How long to wait for further changes before rebuilding
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Watches the content directory, templates, `Config.ron` and the banners,
+/// rebuilding the capsule into `output` whenever they change. Changes which
+/// arrive in quick succession, such as when an editor saves several files,
+/// result in a single rebuild.
+/// # Errors
+/// Returns an error if the files cannot be watched. Errors during a rebuild are
+/// printed and watching continues.
+pub fn watch(output: &Path) -> Result<(), crate::Error> {
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    watcher.watch(Path::new("."), RecursiveMode::NonRecursive)?;
+    watcher.watch(Path::new("content"), RecursiveMode::Recursive)?;
+    if Path::new("templates").exists() {
+        watcher.watch(Path::new("templates"), RecursiveMode::Recursive)?;
+    }
+    watch_banners(&mut watcher)?;
+    println!("{}", gettext("Watching for changes, press Ctrl-C to stop"));
+    while let Ok(event) = rx.recv() {
+        let mut paths = event.map(|e| e.paths).unwrap_or_default();
+        while let Ok(event) = rx.recv_timeout(DEBOUNCE) {
+            paths.extend(event.map(|e| e.paths).unwrap_or_default());
+        }
+        paths.retain(|p| is_watched(p));
+        if paths.is_empty() {
+            continue;
+        }
+        if paths.iter().any(|p| p.ends_with("Config.ron")) {
+            if let Err(e) = crate::reload_config() {
+                eprintln!("{}: {e}", gettext("Error loading config"));
+                continue;
+            }
+            if let Err(e) = watch_banners(&mut watcher) {
+                eprintln!("{}: {e}", gettext("Error watching banners"));
+            }
+        }
+        let start = Instant::now();
+        match build(output) {
+            Ok(summary) => println!(
+                "{} {} {}: {summary}",
+                gettext("Rebuilt in"),
+                start.elapsed().as_millis(),
+                gettext("ms")
+            ),
+            Err(e) => eprintln!("{}: {e}", gettext("Error building capsule")),
         }
-        None => {}
     }
-    capsule.write_tags(&output)?;
-    capsule.write_index(&output)?;
-    capsule.write_gemlog_index(&output)?;
     Ok(())
 }
 
+/// A published page or post found while walking the content directory
+struct Source {
+    /// The path from the content directory to the source file
+    path: PathBuf,
+    /// The path to the rendered file
+    output: PathBuf,
+    /// How deep in the generated capsule this page is
+    depth: usize,
+    /// The link pointing to this page
+    link: Link,
+    /// The collection this page is an entry in, if any
+    collection: Option<String>,
+    /// The number of words in the page
+    words: usize,
+    page: Page,
+    /// Defaults for the directory this page is in
+    section: Section,
+}
+
 /// The metadata extracted from all posts and pages used to construct the rest
 /// of the site
 struct Capsule {
+    /// The directory holding the generated capsule
+    output: PathBuf,
     posts: Posts,
     tags: Tags,
-    banner: Option<String>,
+    pages: Vec<Source>,
+    sections: HashMap<PathBuf, Section>,
+    banners: Banners,
+}
+
+/// Returns the defaults inherited from the parent directory of `path`
+fn inherited(sections: &HashMap<PathBuf, Section>, path: &Path) -> Section {
+    path.parent()
+        .and_then(|p| sections.get(p))
+        .cloned()
+        .unwrap_or_default()
+}
+
+impl Expand for Capsule {
+    fn expand(&self, code: &Shortcode, source: &Path) -> Result<Option<String>, crate::Error> {
+        let src = self.pages.iter().find(|p| p.path == source);
+        // Links are made relative to where the page is written
+        let base = if let Some(s) = src {
+            Url::parse(&s.link.url)?
+        } else {
+            let mut base = crate::load_config().url()?;
+            base.set_path(&source.to_string_lossy());
+            base
+        };
+        let meta = src.map(|p| &p.page.meta);
+        let lang = lang::resolve(source, meta);
+        let expanded = match code.name.as_str() {
+            "posts" => self.list_posts(&base, code, lang.as_deref())?,
+            "tags" => self.list_tags(&base)?,
+            "pages" => {
+                let dir = match code.get("dir") {
+                    Some(d) => PathBuf::from(d),
+                    None => source.parent().map(Path::to_path_buf).unwrap_or_default(),
+                };
+                self.list_pages(&base, source, &dir)?
+            }
+            "tinylog" => self.list_tinylog(&base, code)?,
+            "updated" => self.updated(),
+            "index" => {
+                let page = src.map_or(source, |s| self.permalink(s));
+                let dir = page.parent().unwrap_or(Path::new(""));
+                self.list_dir(&base, dir, self.section(dir).sort())?
+            }
+            // The table of contents is built from the final page content when
+            // the page is written
+            "toc" => format!("{{% {} %}}", code.name),
+            "include" => {
+                let Some(file) = included(code) else {
+                    return Err(gettext("Missing file name for include").into());
+                };
+                fs::read_to_string(file)
+                    .map_err(|e| format!("{}: {file}: {e}", gettext("Error including file")))?
+                    .trim_end()
+                    .to_string()
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(expanded))
+    }
+}
+
+/// Returns the name of the file inserted by an `include` shortcode
+fn included(code: &Shortcode) -> Option<&String> {
+    code.positional.first().or(code.args.get("file"))
 }
 
-impl TryFrom<&Capsule> for Feed {
-    type Error = crate::Error;
+/// Expands shortcodes for counting the words in a page. Included files are
+/// part of the text, while generated listings are not, so every other
+/// shortcode is removed.
+struct Words;
 
-    /// Generates an Atom feed from the metadata
-    fn try_from(capsule: &Capsule) -> Result<Feed, Self::Error> {
+impl Words {
+    /// Counts the words in the content of the page at `source`
+    fn count(content: &str, source: &Path) -> Result<usize, crate::Error> {
+        Ok(stats::word_count(&shortcode::render(
+            content, source, &Self,
+        )?))
+    }
+}
+
+impl Expand for Words {
+    fn expand(&self, code: &Shortcode, _source: &Path) -> Result<Option<String>, crate::Error> {
+        let text = match code.name.as_str() {
+            // Errors are reported when the page itself is rendered
+            "include" => included(code)
+                .and_then(|f| fs::read_to_string(f).ok())
+                .unwrap_or_default(),
+            _ => String::new(),
+        };
+        Ok(Some(text))
+    }
+}
+
+impl Capsule {
+    /// Generates an Atom feed of the posts in `collection` written in `lang`
+    fn atom(&self, collection: &str, lang: Option<&str>) -> Result<Feed, crate::Error> {
         let cfg = crate::load_config();
         let mut entries: Vec<atom::Entry> = vec![];
-        for entry in capsule.posts.values().rev() {
+        for entry in self.posts(Some(collection), lang).rev() {
             entries.push(entry.try_into()?);
         }
-        let year = if let Some(Some(date)) = capsule
-            .posts
-            .values()
+        let year = if let Some(Some(date)) = self
+            .posts(Some(collection), lang)
             .last()
             .map(|post| post.meta.published.as_ref())
         {
@@ -105,288 +531,869 @@ impl TryFrom<&Capsule> for Feed {
         if let Some(p) = &cfg.path {
             url.set_path(p);
         }
+        // The gemlog keeps the title and id it has always had, so that
+        // existing subscriptions are not disturbed
+        let (title, id) = if collection == GEMLOG {
+            (cfg.title.clone(), url.to_string())
+        } else {
+            (
+                format!("{} - {}", cfg.title, heading(collection)),
+                url.join(&format!("{collection}/"))?.to_string(),
+            )
+        };
+        // The id set in the collection's index takes the place of the url, so
+        // that the feed survives a change of domain or path
+        let index = lang::with_lang(&Path::new(collection).join("index.gmi"), lang);
+        let id = Page::from_path(&Path::new("content").join(index))
+            .and_then(|p| p.meta.id)
+            .unwrap_or(id);
         let feed = atom::FeedBuilder::default()
-            .title(cfg.title.to_string())
-            .id(url.to_string())
+            .title(title)
+            .id(id)
             .author(cfg.author.to_atom())
             .rights(atom::Text::plain(format!(
                 "© {year} {} {}",
-                gettext("by"),
+                strings::gettext("by"),
                 &cfg.author.name
             )))
             .base(url.to_string())
+            .lang(lang.or(cfg.lang.as_deref()).map(ToString::to_string))
             .entries(entries)
             .build();
         Ok(feed)
     }
-}
 
-impl Capsule {
+    /// Writes the Atom feed for `collection` in `lang` to disk
+    fn write_atom(
+        &self,
+        output: &Path,
+        collection: &str,
+        lang: Option<&str>,
+    ) -> Result<(), crate::Error> {
+        let atom = self.atom(collection, lang)?;
+        let dest = Feed::get_path(output, Some(Path::new(collection)));
+        atom.to_disk(&lang::with_lang(&dest, lang))
+    }
+
+    /// Returns the posts written in `lang`, oldest first, from `collection` or
+    /// from every collection if it is `None`
+    fn posts<'a>(
+        &'a self,
+        collection: Option<&'a str>,
+        lang: Option<&'a str>,
+    ) -> impl DoubleEndedIterator<Item = &'a Post> {
+        self.posts.values().filter(move |p| {
+            collection.is_none_or(|c| p.collection == c) && lang::matches(p.lang.as_deref(), lang)
+        })
+    }
+
+    /// Returns true if any posts in `collection` are written in `lang`
+    fn has_posts(&self, collection: Option<&str>, lang: Option<&str>) -> bool {
+        self.posts(collection, lang).next().is_some()
+    }
+
+    /// Returns every language used in the capsule. The default language is
+    /// represented as `None` and always comes first.
+    fn languages(&self) -> BTreeSet<Option<String>> {
+        let mut langs = BTreeSet::from([None]);
+        for src in &self.pages {
+            let lang = lang::resolve(&src.path, Some(&src.page.meta));
+            if !lang::is_default(lang.as_deref()) {
+                langs.insert(lang);
+            }
+        }
+        langs
+    }
+
+    /// Returns a list of links to the other language versions of a page
+    fn translations(&self, page: &Source) -> Result<String, crate::Error> {
+        let (stem, _) = lang::split(&page.path);
+        let base = Url::parse(&page.link.url)?;
+        let mut list = String::new();
+        for src in &self.pages {
+            if src.path != page.path && lang::split(&src.path).0 == stem {
+                let lang = lang::resolve(&src.path, Some(&src.page.meta));
+                let url = Self::relative(&base, &src.link.url)?;
+                writeln!(list, "=> {url} {}", lang::label(lang.as_deref()))?;
+            }
+        }
+        if list.is_empty() {
+            Ok(list)
+        } else {
+            Ok(format!(
+                "### {}\n{list}\n",
+                strings::gettext("Also available in")
+            ))
+        }
+    }
+
     /// Walks the "content" directory tree and extracts all of the information
-    /// required to build the site. All pages and gemlog posts are also rendered
-    /// in this function's main loop for efficiency.
+    /// required to build the site. Files other than pages and gemlog posts are
+    /// copied to the output directory in this function's main loop.
     fn init(output: &Path) -> Result<Self, crate::Error> {
+        let cfg = crate::load_config();
         let mut posts: Posts = BTreeMap::new();
-        let mut tags: Tags = HashMap::new();
+        let mut tags = Tags::new();
+        let mut pages = vec![];
+        let mut sections: HashMap<PathBuf, Section> = HashMap::new();
         let mut current = env::current_dir()?;
         current.push("content");
         if !current.exists() {
             fs::create_dir_all(&current)?;
         }
         let current = std::fs::canonicalize(&current)?;
-        let mut index = current.clone();
-        index.push("index.gmi");
-        let mut gemlog_index = current.clone();
-        gemlog_index.push("gemlog");
-        gemlog_index.push("index.gmi");
-        let banner = match crate::banner::get() {
-            Some(Ok(s)) => Some(s.trim_end().to_string()),
-            Some(Err(e)) => {
-                eprintln!("{}: {e}", gettext("Error reading banner file"));
-                return Err(e.into());
-            }
-            None => None,
-        };
+        let collections = cfg.collections();
+        let banners = Banners::load()
+            .inspect_err(|e| eprintln!("{}: {e}", gettext("Error reading banner file")))?;
         for entry in WalkDir::new("content").into_iter().flatten() {
-            let path = PathBuf::from(entry.path());
-            let path = fs::canonicalize(path)?;
+            let path = fs::canonicalize(entry.path())?;
             let last = path.strip_prefix(&current)?;
-            if let Some(n) = last.to_str() {
-                if n == "index.gmi" || n == "gemlog/index.gmi" {
-                    continue;
-                }
+            let base = lang::split(last).0;
+            // Indexes and tag introductions are merged with generated content
+            if base == Path::new("index.gmi")
+                || collections
+                    .keys()
+                    .any(|c| base == Path::new(c).join("index.gmi"))
+                || (base.starts_with(TAGS_DIR) && base.extension().is_some_and(|e| e == "gmi"))
+            {
+                continue;
+            }
+            if entry.file_type().is_dir() {
+                let parent = inherited(&sections, last);
+                let section = match Section::load(&path)? {
+                    Some(s) => parent.merge(&s),
+                    None => parent,
+                };
+                sections.insert(last.to_path_buf(), section);
             }
-            let mut output = output.to_path_buf();
-            output.push(last);
-            if let Some(parent) = output.parent() {
+            let dest = output.join(last);
+            if let Some(parent) = dest.parent() {
                 if !parent.exists() {
                     fs::create_dir_all(parent)?;
                 }
             }
             if let Some(s) = path.extension() {
                 if let Some("gmi") = s.to_str() {
-                    if let Some(page) = Page::from_path(&path) {
-                        if let Some(ref time) = page.meta.published {
-                            if path != index && path != gemlog_index {
-                                let depth = entry.depth();
-                                let link = Link::get(&path, &page.meta)?;
-                                for tag in &page.meta.tags {
-                                    if let Some(t) = tags.get_mut(tag) {
-                                        t.push(link.clone());
-                                    } else {
-                                        tags.insert(tag.to_string(), vec![link.clone()]);
-                                    }
-                                }
-                                if last.starts_with("gemlog") {
-                                    page.write(&output, depth, &banner)?;
-                                    let post = Post {
-                                        link,
-                                        meta: page.meta.clone(),
-                                    };
-                                    posts.insert(time.timestamp()?, post);
-                                } else {
-                                    page.write(&output, depth, &banner)?;
-                                }
+                    if let Some(mut page) = Page::from_path(&path) {
+                        if let Some(time) = page.meta.published {
+                            let section = inherited(&sections, last);
+                            section.apply(&mut page.meta);
+                            let collection = cfg.collection(last);
+                            let permalink =
+                                permalink::path(last, &page.meta, collection.as_deref());
+                            let link = Link::get(&permalink, &page.meta)?;
+                            add_tags(&mut tags, &page.meta.tags, &link);
+                            let words = Words::count(&page.content, last)?;
+                            if let Some(c) = &collection {
+                                let post = Post {
+                                    link: link.clone(),
+                                    meta: page.meta.clone(),
+                                    words,
+                                    lang: lang::resolve(last, Some(&page.meta))
+                                        .filter(|l| !lang::is_default(Some(l))),
+                                    collection: c.clone(),
+                                };
+                                let key = (time.timestamp()?, c.clone(), post.lang.clone());
+                                posts.insert(key, post);
                             }
+                            pages.push(Source {
+                                path: last.to_path_buf(),
+                                output: output.join(&permalink),
+                                depth: permalink.components().count(),
+                                link,
+                                collection,
+                                words,
+                                page,
+                                section,
+                            });
                         }
                     }
-                } else if entry.file_type().is_file() {
-                    fs::copy(&path, &output)?;
+                } else if entry.file_type().is_file()
+                    && entry.file_name() != banner::FILE
+                    && entry.file_name() != section::FILE
+                {
+                    copy_asset(&path, &dest, last)?;
                 }
             } else if entry.file_type().is_file() {
-                fs::copy(&path, &output)?;
+                copy_asset(&path, &dest, last)?;
             }
         }
+        check_paths(&pages, output)?;
+        load_intros(&mut tags);
         Ok(Self {
+            output: output.to_path_buf(),
             posts,
             tags,
-            banner,
+            pages,
+            sections,
+            banners,
         })
     }
 
-    /// Creates a gemtext page for each tag and an index page of all tags
-    fn write_tags(&self, output: &Path) -> Result<(), crate::Error> {
+    /// Expands the shortcodes in every page and gemlog post and writes them to disk
+    fn write_pages(&self) -> Result<(), crate::Error> {
+        for src in &self.pages {
+            let mut page = src.page.clone();
+            page.content = shortcode::render(&page.content, &src.path, self)?;
+            page.content = annotate::links(&page.content, &src.output, &self.output)?;
+            let lang = lang::resolve(&src.path, Some(&page.meta));
+            let all_posts = src.collection.as_ref().map(|c| {
+                let dir = self.permalink(src);
+                let dir = relative_dir(dir.parent().unwrap_or(Path::new("")), Path::new(c));
+                match lang.as_deref() {
+                    Some(l) if !lang::is_default(Some(l)) => format!("{dir}index.{l}.gmi"),
+                    _ if dir.is_empty() => ".".to_string(),
+                    _ => dir,
+                }
+            });
+            let ctx = Context {
+                depth: src.depth,
+                banner: match page.meta.banner {
+                    Some(false) => None,
+                    _ => self.banners.get(&src.path),
+                },
+                section: &src.section,
+                translations: self.translations(src)?,
+                all_posts,
+                words: src.words,
+            };
+            page.write(&src.output, &ctx)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the defaults for the given directory, relative to `content`.
+    /// Directories which only exist in the generated capsule use the defaults
+    /// of their closest parent.
+    fn section(&self, dir: &Path) -> Section {
+        dir.ancestors()
+            .find_map(|d| self.sections.get(d))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Returns the path which a page is written to, relative to the capsule root
+    fn permalink<'a>(&self, src: &'a Source) -> &'a Path {
+        src.output.strip_prefix(&self.output).unwrap_or(&src.output)
+    }
+
+    /// Returns the banner block for an index page, unless it has been turned off
+    /// in the page's metadata or the defaults for it's directory
+    fn banner_for(&self, page: &Page, path: &Path) -> String {
+        let dir = path.parent().unwrap_or(Path::new(""));
+        match page.meta.banner.or(self.section(dir).banner) {
+            Some(false) => String::new(),
+            _ => crate::banner::block(self.banners.get(path)),
+        }
+    }
+
+    /// Returns the path from `base` to the given url
+    fn relative(base: &Url, url: &str) -> Result<String, crate::Error> {
+        let url = Url::parse(url)?;
+        Ok(match base.make_relative(&url) {
+            Some(u) if !u.is_empty() => u,
+            _ => url.to_string(),
+        })
+    }
+
+    /// Lists the most recent posts, optionally limited to a single collection
+    /// or tag. If the capsule has only one collection, that collection is
+    /// listed.
+    fn list_posts(
+        &self,
+        base: &Url,
+        code: &Shortcode,
+        lang: Option<&str>,
+    ) -> Result<String, crate::Error> {
         let cfg = crate::load_config();
-        let tags = gettext("tags");
-        let index_path = Index::get_path(output, Some(&PathBuf::from(&tags)));
-        let mut dest = PathBuf::from(output);
-        dest.push(&tags);
-        if !dest.exists() {
-            fs::create_dir_all(&dest)?;
-        }
-        let fd = File::create(index_path)?;
-        let mut writer = BufWriter::new(fd);
-        let base_url = cfg.url()?;
-        let tags_url = base_url.join(&format!("{tags}/"))?;
-        match &self.banner {
-            Some(s) => writeln!(
-                &mut writer,
-                "```\n{s}\n```# {}\n\n### {}",
-                &cfg.title,
-                gettext("All tags")
-            )?,
-            None => writeln!(
-                &mut writer,
-                "# {}\n\n### {}\n",
-                &cfg.title,
-                gettext("All tags")
-            )?,
+        let collections = cfg.collections();
+        let collection = match code.get("collection") {
+            Some(c) if collections.contains_key(c) => Some(c.to_string()),
+            Some(c) => return Err(format!("{}: {c}", gettext("Unknown collection")).into()),
+            None if collections.len() == 1 => collections.into_keys().next(),
+            None => None,
+        };
+        let collection = collection.as_deref();
+        let num = code
+            .get_usize("count")?
+            .unwrap_or_else(|| collection.map_or(cfg.entries, |c| cfg.entries(c)));
+        let tag = code.get("tag");
+        let title = collection.map_or_else(|| strings::gettext("Recent posts"), heading);
+        let mut posts = format!("### {title}\n");
+        for post in self
+            .posts(collection, lang)
+            .rev()
+            .filter(|p| {
+                tag.is_none_or(|t| {
+                    p.meta
+                        .tags
+                        .iter()
+                        .any(|x| tag::within(&tag::slug(x), &tag::slug(t)))
+                })
+            })
+            .take(num)
+        {
+            let url = Self::relative(base, &post.link.url)?;
+            writeln!(posts, "=> {url} {}", post.link.display)?;
         }
-        for (tag, links) in &self.tags {
-            writeln!(&mut writer, "=> {}.gmi {}", &tag, &tag)?;
-            let mut dest = dest.clone();
-            dest.push(tag);
-            dest.set_extension("gmi");
-            let fd = File::create(dest)?;
-            let mut tagwriter = BufWriter::new(fd);
-            match &self.banner {
-                Some(s) => writeln!(
-                    &mut tagwriter,
-                    "```\n{s}\n```# {}\n\n### {} {}",
-                    &cfg.title,
-                    gettext("Pages tagged"),
-                    &tag
-                )?,
-                None => writeln!(
-                    &mut tagwriter,
-                    "# {}\n\n### {} {}",
-                    &cfg.title,
-                    gettext("Pages tagged"),
-                    &tag
-                )?,
-            }
-            for link in links {
-                let url = if let Some(u) = tags_url.make_relative(&Url::parse(&link.url)?) {
-                    Cow::from(u.to_string())
-                } else {
-                    Cow::from(&link.url)
-                };
-                writeln!(&mut tagwriter, "=> {url} {}", link.display)?;
+        let Some(collection) = collection else {
+            writeln!(posts)?;
+            return Ok(posts);
+        };
+        let all = match lang {
+            Some(l) if !lang::is_default(lang) => format!("{collection}/index.{l}.gmi"),
+            _ => format!("{collection}/"),
+        };
+        let all = Self::relative(base, cfg.url()?.join(&all)?.as_str())?;
+        writeln!(posts, "=> {all} {}\n", strings::gettext("All posts"))?;
+        Ok(posts)
+    }
+
+    /// Lists every tag along with the number of pages carrying it or any tag
+    /// below it. Tags are listed beneath their parent, indented by their depth.
+    fn list_tags(&self, base: &Url) -> Result<String, crate::Error> {
+        let cfg = crate::load_config();
+        let tags_url = cfg.url()?.join(&format!("{TAGS_DIR}/"))?;
+        let mut slugs = self.tags.keys().collect::<Vec<_>>();
+        slugs.sort_by_key(|slug| slug.split(tag::SEPARATOR).collect::<Vec<_>>());
+        let mut list = String::new();
+        for slug in slugs {
+            let tagged = &self.tags[slug];
+            let url = tags_url.join(&tag::file(slug))?;
+            write!(
+                list,
+                "=> {} {}{} ({})",
+                Self::relative(base, url.as_str())?,
+                "· ".repeat(slug.matches(tag::SEPARATOR).count()),
+                tagged.label(),
+                self.tagged(slug).len()
+            )?;
+            match tagged.summary() {
+                Some(summary) => writeln!(list, " - {summary}")?,
+                None => writeln!(list)?,
+            }
+        }
+        Ok(list)
+    }
+
+    /// Returns links to every page carrying the tag `slug` or any tag below it
+    fn tagged(&self, slug: &str) -> Vec<&Link> {
+        let mut links: Vec<&Link> = vec![];
+        for (_, t) in self.tags.iter().filter(|(s, _)| tag::within(s, slug)) {
+            for link in &t.links {
+                if !links.contains(&link) {
+                    links.push(link);
+                }
+            }
+        }
+        links
+    }
+
+    /// Lists the published pages found under the given directory
+    fn list_pages(&self, base: &Url, source: &Path, dir: &Path) -> Result<String, crate::Error> {
+        let mut pages = self
+            .pages
+            .iter()
+            .filter(|p| p.path.starts_with(dir) && p.path != source)
+            .collect::<Vec<_>>();
+        pages.sort_by(|a, b| a.page.meta.title.cmp(&b.page.meta.title));
+        let mut list = String::new();
+        for src in pages {
+            let url = Self::relative(base, &src.link.url)?;
+            writeln!(list, "=> {url} {}", src.page.meta.title)?;
+        }
+        Ok(list)
+    }
+
+    /// Lists the subdirectories of `dir` which contain published pages, followed
+    /// by the published pages in `dir` itself. Pages are grouped by where they
+    /// are written in the generated capsule.
+    fn list_dir(&self, base: &Url, dir: &Path, sort: SortBy) -> Result<String, crate::Error> {
+        let cfg = crate::load_config();
+        let mut subdirs = self
+            .pages
+            .iter()
+            .filter_map(|p| self.permalink(p).strip_prefix(dir).ok())
+            .filter(|p| p.components().count() > 1)
+            .filter_map(|p| p.components().next())
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        subdirs.sort();
+        subdirs.dedup();
+        let mut list = String::new();
+        if !subdirs.is_empty() {
+            writeln!(list, "### {}", strings::gettext("Directories"))?;
+            for sub in subdirs {
+                let mut url = cfg.url()?;
+                url.set_path(&format!("{}/{sub}/", dir.display()));
+                let url = Self::relative(base, url.as_str())?;
+                writeln!(list, "=> {url} {sub}")?;
+            }
+            writeln!(list)?;
+        }
+        let mut pages = self
+            .pages
+            .iter()
+            .filter(|p| {
+                let path = self.permalink(p);
+                path.parent() == Some(dir) && !path.ends_with("index.gmi")
+            })
+            .collect::<Vec<_>>();
+        match sort {
+            SortBy::Title => pages.sort_by(|a, b| a.page.meta.title.cmp(&b.page.meta.title)),
+            SortBy::Date => pages.sort_by_key(|p| {
+                cmp::Reverse(p.page.meta.published.and_then(|t| t.timestamp().ok()))
+            }),
+        }
+        if !pages.is_empty() {
+            writeln!(list, "### {}", strings::gettext("Pages"))?;
+            for src in pages {
+                let url = Self::relative(base, &src.link.url)?;
+                writeln!(list, "=> {url} {}", src.link.display)?;
+                if let Some(summary) = &src.page.meta.summary {
+                    writeln!(list, "{summary}")?;
+                }
             }
+        }
+        Ok(list)
+    }
+
+    /// Renders the most recent tinylog entries
+    fn list_tinylog(&self, base: &Url, code: &Shortcode) -> Result<String, crate::Error> {
+        let Some(src) = self
+            .pages
+            .iter()
+            .find(|p| p.path == Path::new("tinylog.gmi"))
+        else {
+            return Ok(String::new());
+        };
+        let num = code.get_usize("count")?.unwrap_or(3);
+        let log: Tinylog<Time> = src.page.content.parse()?;
+        let mut list = String::new();
+        for entry in log.entries.values().rev().take(num) {
+            writeln!(list, "#{entry}")?;
+        }
+        let url = Self::relative(base, &src.link.url)?;
+        writeln!(list, "=> {url} {}", src.page.meta.title)?;
+        Ok(list)
+    }
+
+    /// Returns the date of the most recently published page or post
+    fn updated(&self) -> String {
+        self.pages
+            .iter()
+            .filter_map(|p| p.page.meta.published)
+            .max_by_key(|t| t.timestamp().unwrap_or_default())
+            .unwrap_or_else(Time::now)
+            .date_string()
+    }
+
+    /// Creates a gemtext page for each tag and an index page of all tags. The
+    /// introduction for each tag, and for the index in `content/tags/index.gmi`,
+    /// is placed before the generated listing.
+    fn write_tags(&self, output: &Path) -> Result<(), crate::Error> {
+        let cfg = crate::load_config();
+        fs::create_dir_all(output.join(TAGS_DIR))?;
+        let index_path = Index::get_path(output, Some(&PathBuf::from(TAGS_DIR)));
+        let source = Path::new(TAGS_DIR).join("index.gmi");
+        let page = Page::from_path(&Path::new("content").join(&source)).unwrap_or_default();
+        let tags_url = cfg.url()?.join(&format!("{TAGS_DIR}/"))?;
+        let tmpl = Template::get(page.meta.template.as_deref(), template::Kind::Tags)?;
+        let year = Utc::now().date_naive().year();
+        let index = Vars {
+            banner: crate::banner::block(self.banners.get(Path::new(TAGS_DIR))),
+            title: cfg.title.clone(),
+            content: toc::fill(&format!(
+                "{}### {}\n{}",
+                self.intro(&page, &source, &index_path)?,
+                strings::gettext("All tags"),
+                self.list_tags(&tags_url)?
+            ))?,
+            nav: format!("=> .. {}\n", strings::gettext("Home")),
+            footer: crate::footer(year, &cfg.footer_links)?,
+            ..Default::default()
+        };
+        for (slug, tagged) in &self.tags {
+            self.write_tag(output, slug, tagged, &index)?;
+        }
+        fs::write(index_path, tmpl.render(&index))?;
+        Ok(())
+    }
+
+    /// Writes the page for a single tag. The banner and footer are taken from
+    /// the tags index.
+    fn write_tag(
+        &self,
+        output: &Path,
+        slug: &str,
+        tagged: &Tagged,
+        index: &Vars,
+    ) -> Result<(), crate::Error> {
+        let cfg = crate::load_config();
+        let tag = tagged.name();
+        let tags_url = cfg.url()?.join(&format!("{TAGS_DIR}/"))?;
+        let url = tags_url.join(&tag::file(slug))?;
+        let source = Path::new(TAGS_DIR).join(format!("{slug}.gmi"));
+        let dest = output.join(&source);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let intro = tagged.intro.clone().unwrap_or_default();
+        let mut vars = Vars {
+            banner: index.banner.clone(),
+            title: cfg.title.clone(),
+            content: format!(
+                "{}### {} {tag}\n",
+                self.intro(&intro, &source, &dest)?,
+                strings::gettext("Pages tagged")
+            ),
+            nav: self.tag_nav(slug, &url)?,
+            footer: index.footer.clone(),
+            ..Default::default()
+        };
+        for link in self.tagged(slug) {
             writeln!(
-                &mut tagwriter,
-                "=> . {}\n=> .. {}",
-                gettext("All tags"),
-                gettext("Home"),
+                vars.content,
+                "=> {} {}",
+                Self::relative(&url, &link.url)?,
+                link.display
             )?;
-            let year = Utc::now().date_naive().year();
-            crate::write_footer(&mut tagwriter, year)?;
         }
-        writeln!(&mut writer, "\n=> .. Home")?;
-        let year = Utc::now().date_naive().year();
-        crate::write_footer(&mut writer, year)?;
+        let children = self
+            .tags
+            .iter()
+            .filter(|(s, _)| tag::parents(s).last() == Some(slug))
+            .collect::<Vec<_>>();
+        if !children.is_empty() {
+            writeln!(
+                vars.content,
+                "\n### {} {tag}",
+                strings::gettext("Tags within")
+            )?;
+            for (child_slug, child) in children {
+                let child_url = tags_url.join(&tag::file(child_slug))?;
+                writeln!(
+                    vars.content,
+                    "=> {} {}",
+                    Self::relative(&url, child_url.as_str())?,
+                    child.name()
+                )?;
+            }
+        }
+        vars.content = toc::fill(&vars.content)?;
+        let tmpl = Template::get(intro.meta.template.as_deref(), template::Kind::Tags)?;
+        fs::write(dest, tmpl.render(&vars))?;
         Ok(())
     }
 
+    /// Renders the introduction placed before a generated listing, with it's
+    /// summary first. Returns an empty string if there is no introduction.
+    fn intro(&self, page: &Page, source: &Path, dest: &Path) -> Result<String, crate::Error> {
+        let mut intro = String::new();
+        if let Some(summary) = &page.meta.summary {
+            writeln!(intro, "{summary}\n")?;
+        }
+        if !page.content.is_empty() {
+            let content = shortcode::render(&page.content, source, self)?;
+            writeln!(
+                intro,
+                "{}\n",
+                annotate::links(&content, dest, &self.output)?
+            )?;
+        }
+        Ok(intro)
+    }
+
+    /// Returns the navigation links for a tag's page at `url`, leading up
+    /// through each of the tag's parents to the tags index
+    fn tag_nav(&self, slug: &str, url: &Url) -> Result<String, crate::Error> {
+        let tags_url = crate::load_config().url()?.join(&format!("{TAGS_DIR}/"))?;
+        let mut nav = String::new();
+        for parent in tag::parents(slug) {
+            if let Some(t) = self.tags.get(parent) {
+                let parent_url = tags_url.join(&tag::file(parent))?;
+                writeln!(
+                    nav,
+                    "=> {} {}",
+                    Self::relative(url, parent_url.as_str())?,
+                    t.name()
+                )?;
+            }
+        }
+        // The tags index is in the directory above the first level of the tag
+        let up = vec![".."; slug.matches(tag::SEPARATOR).count()];
+        let index = if up.is_empty() {
+            ".".to_string()
+        } else {
+            up.join("/")
+        };
+        writeln!(
+            nav,
+            "=> {index} {}\n=> {} {}",
+            strings::gettext("All tags"),
+            [up, vec![".."]].concat().join("/"),
+            strings::gettext("Home"),
+        )?;
+        Ok(nav)
+    }
+
     /// Renders the capsule main index and writes it to disk
-    fn write_index(&self, output: &Path) -> Result<(), crate::Error> {
+    fn write_index(&self, output: &Path, lang: Option<&str>) -> Result<(), crate::Error> {
         let cfg = crate::load_config();
-        let origin: PathBuf = ["content", "index.gmi"].iter().collect();
-        let outfile = Index::get_path(&PathBuf::from(output), None);
-        let fd = File::create(outfile)?;
-        let mut writer = BufWriter::new(fd);
+        let source = lang::with_lang(Path::new("index.gmi"), lang);
+        let origin = Path::new("content").join(&source);
+        let outfile = lang::with_lang(&Index::get_path(&PathBuf::from(output), None), lang);
         let page = if let Some(p) = Page::from_path(&origin) {
             p
-        } else {
+        } else if lang::is_default(lang) {
             let mut idx = Page::default();
-            idx.content.push_str("{% posts %}");
+            for collection in cfg.collections().keys() {
+                writeln!(idx.content, "{{% posts collection={collection} %}}")?;
+            }
             idx
+        } else {
+            return Ok(());
         };
-        match &self.banner {
-            Some(s) => writeln!(&mut writer, "```\n{s}\n```# {}\n", &cfg.title)?,
-            None => writeln!(&mut writer, "# {}\n", &cfg.title)?,
-        }
-        let mut posts = format!("### {}\n", gettext("Gemlog posts"));
-        let num = cmp::min(cfg.entries, self.posts.len());
-        let base = cfg.url()?;
-        for post in self.posts.values().rev().take(num) {
-            let url = Url::parse(&post.link.url)?;
-            let url = if let Some(u) = base.make_relative(&url) {
-                Cow::from(u.to_string())
-            } else {
-                Cow::from(&post.link.url)
-            };
-            writeln!(posts, "=> {url} {}", post.link.display)?;
-        }
-        writeln!(posts, "=> gemlog/ {}\n", gettext("All posts"))?;
-        let content = page.content.replace("{% posts %}", &posts);
-        writeln!(&mut writer, "{content}")?;
         let year = Utc::now().date_naive().year();
-        crate::write_footer(&mut writer, year)?;
+        let vars = Vars {
+            banner: self.banner_for(&page, &source),
+            title: cfg.title.clone(),
+            content: annotate::links(
+                &toc::fill(&shortcode::render(&page.content, &source, self)?)?,
+                &outfile,
+                &self.output,
+            )?,
+            translations: self.index_translations(Path::new(""), lang)?,
+            footer: crate::footer(year, self.section(Path::new("")).footer_links())?,
+            ..Default::default()
+        };
+        let tmpl = Template::get(page.meta.template.as_deref(), template::Kind::Index)?;
+        fs::write(outfile, tmpl.render(&vars))?;
         Ok(())
     }
 
-    /// Renders the gemlog index and writes it to disk
-    fn write_gemlog_index(&self, output: &Path) -> Result<(), crate::Error> {
-        let cfg = crate::load_config();
-        let origin: PathBuf = ["content", "gemlog", "index.gmi"].iter().collect();
-        let outfile = Index::get_path(&PathBuf::from(output), Some(&PathBuf::from("gemlog")));
-        let fd = File::create(outfile)?;
-        let mut writer = BufWriter::new(fd);
-        let page = if let Some(p) = Page::from_path(&origin) {
-            p
+    /// Returns links to the other language versions of the index in `dir`
+    fn index_translations(&self, dir: &Path, lang: Option<&str>) -> Result<String, crate::Error> {
+        let mut list = String::new();
+        for other in self.languages() {
+            let other = other.as_deref();
+            if lang::matches(other, lang) {
+                continue;
+            }
+            let index = lang::with_lang(&dir.join("index.gmi"), other);
+            let exists = Path::new("content").join(&index).exists()
+                || dir.to_str().is_some_and(|d| self.has_posts(Some(d), other))
+                || lang::is_default(other);
+            if exists {
+                let file = index.file_name().unwrap_or_default().to_string_lossy();
+                writeln!(list, "=> {file} {}", lang::label(other))?;
+            }
+        }
+        if list.is_empty() {
+            Ok(list)
         } else {
-            Page::default()
-        };
-        match &self.banner {
-            Some(s) => write!(&mut writer, "```\n{s}\n```# {}\n\n", &cfg.title)?,
-            None => write!(&mut writer, "# {}\n\n", &cfg.title)?,
+            Ok(format!(
+                "### {}\n{list}\n",
+                strings::gettext("Also available in")
+            ))
         }
-        write!(
-            &mut writer,
+    }
+
+    /// Renders the index of a collection and writes it to disk
+    fn write_collection_index(
+        &self,
+        output: &Path,
+        collection: &str,
+        lang: Option<&str>,
+    ) -> Result<(), crate::Error> {
+        let cfg = crate::load_config();
+        let dir = Path::new(collection);
+        let source = lang::with_lang(&dir.join("index.gmi"), lang);
+        let origin = Path::new("content").join(&source);
+        let outfile = lang::with_lang(&Index::get_path(output, Some(dir)), lang);
+        let page = Page::from_path(&origin).unwrap_or_default();
+        let mut content = format!(
             "{}\n\n### {}\n",
-            &page.content,
-            gettext("Gemlog posts"),
-        )?;
+            annotate::links(
+                &shortcode::render(&page.content, &source, self)?,
+                &outfile,
+                &self.output
+            )?,
+            heading(collection)
+        );
         let base = cfg.url()?;
-        let base = base.join("gemlog/index.gmi")?;
-        for post in self.posts.values().rev() {
+        let base = base.join(&format!("{collection}/index.gmi"))?;
+        for post in self.posts(Some(collection), lang).rev() {
             let url = Url::parse(&post.link.url)?;
             let url = if let Some(u) = base.make_relative(&url) {
                 Cow::from(u.to_string())
             } else {
                 Cow::from(&post.link.url)
             };
-            writeln!(&mut writer, "=> {url} {}", post.link.display)?;
+            match stats::line(post.words) {
+                Some(line) => writeln!(content, "=> {url} {} ({line})", post.link.display)?,
+                None => writeln!(content, "=> {url} {}", post.link.display)?,
+            }
         }
-        match &cfg.feed {
+        let atom = lang::with_lang(Path::new("atom.xml"), lang);
+        let atom = atom.display();
+        let gemfeed = lang::with_lang(Path::new("feed.gmi"), lang);
+        let gemfeed = gemfeed.display();
+        match cfg.feed(collection) {
             Some(crate::config::Feed::Atom) => {
-                writeln!(&mut writer, "\n=> atom.xml {}", gettext("Atom Feed"))?;
+                writeln!(content, "\n=> {atom} {}", strings::gettext("Atom Feed"))?;
             }
             Some(crate::config::Feed::Gemini) => {
-                writeln!(&mut writer, "\n=> feed.gmi {}", gettext("Gemini Feed"))?;
+                writeln!(
+                    content,
+                    "\n=> {gemfeed} {}",
+                    strings::gettext("Gemini Feed")
+                )?;
             }
             Some(crate::config::Feed::Both) => {
                 writeln!(
-                    &mut writer,
-                    "\n=> atom.xml {}\n=> feed.gmi {}",
-                    gettext("Atom Feed"),
-                    gettext("Gemini Feed")
+                    content,
+                    "\n=> {atom} {}\n=> {gemfeed} {}",
+                    strings::gettext("Atom Feed"),
+                    strings::gettext("Gemini Feed")
                 )?;
             }
             None => {}
         }
-        writeln!(
-            &mut writer,
-            "\n=> ../{} {}\n=> .. {}",
-            gettext("tags"),
-            gettext("tags"),
-            gettext("Home"),
-        )?;
         let year = Utc::now().date_naive().year();
-        crate::write_footer(&mut writer, year)?;
+        let home = vec![".."; dir.components().count()].join("/");
+        let vars = Vars {
+            banner: self.banner_for(&page, &source),
+            title: cfg.title.clone(),
+            content: toc::fill(&content)?,
+            translations: self.index_translations(dir, lang)?,
+            nav: format!(
+                "=> {home}/{TAGS_DIR} {}\n=> {home} {}\n",
+                strings::gettext("tags"),
+                strings::gettext("Home"),
+            ),
+            footer: crate::footer(year, self.section(dir).footer_links())?,
+            ..Default::default()
+        };
+        let tmpl = Template::get(page.meta.template.as_deref(), template::Kind::Index)?;
+        fs::write(outfile, tmpl.render(&vars))?;
         Ok(())
     }
 
-    fn write_gemfeed(&self, output: &Path) -> Result<(), crate::Error> {
+    /// Writes a page at each alias of every page, linking to it's new location
+    fn write_stubs(&self) -> Result<(), crate::Error> {
         let cfg = crate::load_config();
-        let mut outfile = output.to_path_buf();
-        outfile.push("gemlog");
-        outfile.push("feed.gmi");
-        let fd = File::create(outfile)?;
+        for src in &self.pages {
+            for alias in &src.page.meta.aliases {
+                let alias = permalink::alias(alias);
+                let dest = self.output.join(&alias);
+                if dest.exists() {
+                    return Err(format!(
+                        "{}: {}",
+                        gettext("An alias is the path of a generated file"),
+                        alias.display()
+                    )
+                    .into());
+                }
+                if let Some(parent) = dest.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                let mut base = cfg.url()?;
+                base.set_path(&alias.to_string_lossy());
+                let url = Self::relative(&base, &src.link.url)?;
+                let title = &src.page.meta.title;
+                fs::write(
+                    dest,
+                    format!(
+                        "# {title}\n\n{}\n=> {url} {title}\n",
+                        strings::gettext("This page has moved.")
+                    ),
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Generates an index page for every directory of the generated capsule
+    /// which holds published pages and does not have an index of it's own
+    fn write_dir_indexes(&self, output: &Path) -> Result<(), crate::Error> {
+        let cfg = crate::load_config();
+        let tmpl = Template::get(None, template::Kind::Index)?;
+        let year = Utc::now().date_naive().year();
+        let collections = cfg.collections();
+        let dirs = self
+            .pages
+            .iter()
+            .flat_map(|p| self.permalink(p).ancestors().skip(1))
+            .collect::<BTreeSet<_>>();
+        for dir in dirs {
+            let index = dir.join("index.gmi");
+            let section = self.section(dir);
+            if dir.as_os_str().is_empty()
+                || collections.keys().any(|c| dir == Path::new(c))
+                || dir.starts_with(TAGS_DIR)
+                || !section.index()
+                || self.pages.iter().any(|p| self.permalink(p) == index)
+                || Path::new("content").join(&index).exists()
+            {
+                continue;
+            }
+            let mut base = cfg.url()?;
+            base.set_path(&index.to_string_lossy());
+            // Directories holding only assets, or pages which are not listed,
+            // get no index
+            let content = self.list_dir(&base, dir, section.sort())?;
+            if content.is_empty() {
+                continue;
+            }
+            let depth = dir.components().count();
+            let home = "../".repeat(depth);
+            let vars = Vars {
+                banner: match section.banner {
+                    Some(false) => String::new(),
+                    _ => crate::banner::block(self.banners.get(&index)),
+                },
+                title: dir
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+                content,
+                nav: format!(
+                    "=> ../ {}\n=> {home} {}\n",
+                    strings::gettext("Up"),
+                    strings::gettext("Home")
+                ),
+                footer: crate::footer(year, section.footer_links())?,
+                ..Default::default()
+            };
+            let outfile = Index::get_path(output, Some(dir));
+            if let Some(parent) = outfile.parent() {
+                if !parent.exists() {
+                    fs::create_dir_all(parent)?;
+                }
+            }
+            fs::write(outfile, tmpl.render(&vars))?;
+        }
+        Ok(())
+    }
+
+    /// Writes the Gemini feed for `collection` in `lang` to disk
+    fn write_gemfeed(
+        &self,
+        output: &Path,
+        collection: &str,
+        lang: Option<&str>,
+    ) -> Result<(), crate::Error> {
+        let cfg = crate::load_config();
+        let outfile = output.join(collection).join("feed.gmi");
+        let fd = File::create(lang::with_lang(&outfile, lang))?;
         let mut writer = BufWriter::new(fd);
-        writeln!(&mut writer, "# {}\n", &cfg.title)?;
-        for entry in self.posts.values().rev() {
+        if collection == GEMLOG {
+            writeln!(&mut writer, "# {}\n", &cfg.title)?;
+        } else {
+            writeln!(&mut writer, "# {} - {}\n", &cfg.title, heading(collection))?;
+        }
+        for entry in self.posts(Some(collection), lang).rev() {
             writeln!(&mut writer, "{}", entry.link,)?;
         }
         Ok(())
@@ -0,0 +1,655 @@
+use {
+    super::serve::mime_type,
+    crate::config::Target,
+    clap::ArgMatches,
+    gettextrs::gettext,
+    ron::ser::{to_string_pretty, PrettyConfig},
+    rustls::{
+        client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+        crypto::{self, WebPkiSupportedAlgorithms},
+        pki_types::{CertificateDer, ServerName, UnixTime},
+        ClientConfig, ClientConnection, DigitallySignedStruct, SignatureScheme, StreamOwned,
+    },
+    sha2::{Digest, Sha256},
+    std::{
+        collections::BTreeMap,
+        fmt::Write as _,
+        fs,
+        io::{BufRead, BufReader, Write},
+        net::TcpStream,
+        path::{Path, PathBuf},
+        process,
+        sync::{Arc, Mutex, PoisonError},
+    },
+    url::Url,
+    walkdir::WalkDir,
+};
+
+/// The directory holding the manifest of what was last deployed to each target
+const MANIFESTS: &str = ".deploy";
+
+/// A map of file paths, relative to the capsule root, to the hash of their
+/// content
+type Manifest = BTreeMap<String, String>;
+
+/// Deploys the generated capsule to one of the targets in `Config.ron`
+/// # Errors
+/// Errors are bubbled up from the called functions
+pub fn run(matches: &ArgMatches) -> Result<(), crate::Error> {
+    let cfg = crate::load_config();
+    let name = match matches.get_one::<String>("target") {
+        Some(n) => n.clone(),
+        None if cfg.deploy.len() == 1 => cfg.deploy.keys().next().cloned().unwrap_or_default(),
+        None => return Err(gettext("A deploy target must be given").into()),
+    };
+    let Some(target) = cfg.deploy.get(&name) else {
+        return Err(format!("{}: {name}", gettext("Unknown deploy target")).into());
+    };
+    let mut root = PathBuf::from(
+        matches
+            .get_one::<String>("output")
+            .map_or("public", String::as_str),
+    );
+    if let Some(p) = &cfg.path {
+        root.push(p);
+    }
+    if !root.exists() {
+        return Err(format!(
+            "{}: {}",
+            gettext("Capsule has not been built"),
+            root.display()
+        )
+        .into());
+    }
+    let deploy = Deploy::new(&name, target, fs::canonicalize(root)?, manifest_path(&name))?;
+    if matches.get_flag("dry-run") {
+        deploy.list();
+        Ok(())
+    } else {
+        deploy.run()
+    }
+}
+
+/// The changes needed to bring a deploy target up to date
+struct Deploy<'a> {
+    /// The name of the target
+    name: &'a str,
+    /// Where the capsule is being deployed to
+    target: &'a Target,
+    /// The directory holding the generated capsule
+    root: PathBuf,
+    /// Where the manifest for the target is saved
+    manifest: PathBuf,
+    /// The files in the capsule as it was last deployed
+    previous: Manifest,
+    /// The files in the capsule as it is now
+    current: Manifest,
+    /// The fingerprint which the certificate of a Titan server must match,
+    /// once it is known
+    pin: Arc<Mutex<Option<String>>>,
+}
+
+impl<'a> Deploy<'a> {
+    /// Compares the generated capsule with the manifest from the last deploy
+    fn new(
+        name: &'a str,
+        target: &'a Target,
+        root: PathBuf,
+        manifest: PathBuf,
+    ) -> Result<Self, crate::Error> {
+        let previous = if manifest.exists() {
+            ron::de::from_str(&fs::read_to_string(&manifest)?)?
+        } else {
+            Manifest::new()
+        };
+        let trusted = manifest.with_extension("cert");
+        let pin = match target {
+            Target::Titan {
+                fingerprint: Some(f),
+                ..
+            } => Some(f.to_lowercase().replace(':', "")),
+            Target::Titan { .. } if trusted.exists() => {
+                Some(fs::read_to_string(&trusted)?.trim().to_string())
+            }
+            _ => None,
+        };
+        let mut current = Manifest::new();
+        for entry in WalkDir::new(&root).into_iter().flatten() {
+            if entry.file_type().is_file() {
+                let path = entry.path().strip_prefix(&root)?;
+                let hash = sha256(&fs::read(entry.path())?);
+                current.insert(path.to_string_lossy().to_string(), hash);
+            }
+        }
+        Ok(Self {
+            name,
+            target,
+            root,
+            manifest,
+            previous,
+            current,
+            pin: Arc::new(Mutex::new(pin)),
+        })
+    }
+
+    /// Returns the files which are new or have changed since the last deploy
+    fn changed(&self) -> impl Iterator<Item = &String> {
+        self.current
+            .iter()
+            .filter(|(path, hash)| self.previous.get(*path) != Some(*hash))
+            .map(|(path, _)| path)
+    }
+
+    /// Returns the files which were deployed previously but are no longer part
+    /// of the capsule, if the target is set to delete them
+    fn stale(&self) -> impl Iterator<Item = &String> {
+        let delete = match self.target {
+            Target::Directory { delete, .. } | Target::Titan { delete, .. } => *delete,
+            Target::Command { remove, .. } => remove.is_some(),
+        };
+        self.previous
+            .keys()
+            .filter(move |path| delete && !self.current.contains_key(*path))
+    }
+
+    /// Prints the changes which would be made, without making them
+    fn list(&self) {
+        let mut count = 0;
+        for path in self.changed() {
+            println!("{} {path}", gettext("upload"));
+            count += 1;
+        }
+        for path in self.stale() {
+            println!("{} {path}", gettext("delete"));
+            count += 1;
+        }
+        println!(
+            "{count} {} {} ({})",
+            gettext("changes for"),
+            self.name,
+            gettext("dry run")
+        );
+    }
+
+    /// Uploads changed files and deletes stale ones, then saves the manifest.
+    /// If an upload fails, the manifest records the files which were deployed
+    /// before the failure.
+    fn run(&self) -> Result<(), crate::Error> {
+        let mut manifest = self.previous.clone();
+        let changed: Vec<_> = self.changed().cloned().collect();
+        let stale: Vec<_> = self.stale().cloned().collect();
+        let mut result = Ok(());
+        for path in &changed {
+            if let Err(e) = self.upload(path) {
+                result = Err(e);
+                break;
+            }
+            println!("{} {path}", gettext("uploaded"));
+            manifest.insert(path.clone(), self.current[path].clone());
+        }
+        if result.is_ok() {
+            for path in &stale {
+                if let Err(e) = self.remove(path) {
+                    result = Err(e);
+                    break;
+                }
+                println!("{} {path}", gettext("deleted"));
+                manifest.remove(path);
+            }
+        }
+        if let Some(dir) = self.manifest.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(
+            &self.manifest,
+            to_string_pretty(&manifest, PrettyConfig::new())?,
+        )?;
+        self.trust()?;
+        if result.is_ok() {
+            println!(
+                "{} {}: {} {}, {} {}",
+                gettext("Deployed to"),
+                self.name,
+                changed.len(),
+                gettext("uploaded"),
+                stale.len(),
+                gettext("deleted")
+            );
+        }
+        result
+    }
+
+    /// Records the certificate of a Titan server the first time it is seen,
+    /// unless a fingerprint is set in the config
+    fn trust(&self) -> Result<(), crate::Error> {
+        let file = self.manifest.with_extension("cert");
+        if let Target::Titan {
+            fingerprint: None, ..
+        } = self.target
+        {
+            let pin = self.pin.lock().unwrap_or_else(PoisonError::into_inner);
+            if let (Some(pin), false) = (pin.as_ref(), file.exists()) {
+                fs::write(&file, pin)?;
+                println!(
+                    "{} {}: {pin}",
+                    gettext("Trusting the certificate of"),
+                    self.name
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends a single file to the target
+    fn upload(&self, path: &str) -> Result<(), crate::Error> {
+        let local = self.root.join(path);
+        match self.target {
+            Target::Directory { path: dir, .. } => {
+                let dest = dir.join(path);
+                if let Some(parent) = dest.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::copy(&local, &dest)?;
+                Ok(())
+            }
+            Target::Command { upload, .. } => command(upload, &self.root, path),
+            Target::Titan { url, token, .. } => titan(
+                url,
+                path,
+                token.as_deref(),
+                &fs::read(&local)?,
+                mime_type(&local),
+                &self.pin,
+            ),
+        }
+    }
+
+    /// Deletes a single file from the target
+    fn remove(&self, path: &str) -> Result<(), crate::Error> {
+        match self.target {
+            Target::Directory { path: dir, .. } => {
+                let dest = dir.join(path);
+                if dest.exists() {
+                    fs::remove_file(dest)?;
+                }
+                Ok(())
+            }
+            Target::Command { remove, .. } => match remove {
+                Some(cmd) => command(cmd, &self.root, path),
+                None => Ok(()),
+            },
+            // A zero length upload deletes the resource
+            Target::Titan { url, token, .. } => {
+                titan(url, path, token.as_deref(), &[], "text/plain", &self.pin)
+            }
+        }
+    }
+}
+
+/// Returns the path to the manifest for the named target
+fn manifest_path(name: &str) -> PathBuf {
+    [MANIFESTS, &format!("{name}.ron")].iter().collect()
+}
+
+/// Returns the SHA-256 hash of `data` as a hex string
+fn sha256(data: &[u8]) -> String {
+    Sha256::digest(data).iter().fold(String::new(), |mut s, b| {
+        let _ = write!(s, "{b:02x}");
+        s
+    })
+}
+
+/// Quotes a string so that the shell treats it as a single word
+fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// Replaces the placeholders in a command template with the quoted paths to a
+/// single file. The template is scanned once, so that a placeholder appearing
+/// within a path is not replaced.
+fn expand(template: &str, local: &str, path: &str) -> String {
+    let mut cmd = String::new();
+    let mut rest = template;
+    while let Some(i) = rest.find('{') {
+        cmd.push_str(&rest[..i]);
+        rest = &rest[i..];
+        if let Some(r) = rest.strip_prefix("{local}") {
+            cmd.push_str(&quote(local));
+            rest = r;
+        } else if let Some(r) = rest.strip_prefix("{path}") {
+            cmd.push_str(&quote(path));
+            rest = r;
+        } else {
+            cmd.push('{');
+            rest = &rest[1..];
+        }
+    }
+    cmd.push_str(rest);
+    cmd
+}
+
+/// Runs a command template for a single file using the shell, from the
+/// directory holding the capsule root
+fn command(template: &str, root: &Path, path: &str) -> Result<(), crate::Error> {
+    let cmd = expand(template, &root.join(path).to_string_lossy(), path);
+    let status = process::Command::new("sh")
+        .args(["-c", &cmd])
+        .current_dir(root)
+        .status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("{}: {cmd}", gettext("Command failed")).into())
+    }
+}
+
+/// Returns the url of the file at `path` under the titan url `base`, and the
+/// request line for uploading `size` bytes to it, without the terminating CRLF
+fn request(
+    base: &str,
+    path: &str,
+    token: Option<&str>,
+    size: usize,
+    mime: &str,
+) -> Result<(Url, String), crate::Error> {
+    let url = Url::parse(base)?.join(path)?;
+    let mut request = format!("{url};mime={mime};size={size}");
+    if let Some(t) = token {
+        write!(request, ";token={t}")?;
+    }
+    Ok((url, request))
+}
+
+/// Uploads `body` to the file at `path` under the titan url `base`. The
+/// server's certificate must match `pin`, or is pinned if it is the first seen.
+fn titan(
+    base: &str,
+    path: &str,
+    token: Option<&str>,
+    body: &[u8],
+    mime: &str,
+    pin: &Arc<Mutex<Option<String>>>,
+) -> Result<(), crate::Error> {
+    let (url, request) = request(base, path, token, body.len(), mime)?;
+    let host = url.host_str().unwrap_or_default().to_string();
+    let port = url.port().unwrap_or(1965);
+    let tls = ClientConfig::builder()
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(Pinned::new(Arc::clone(pin))))
+        .with_no_client_auth();
+    let name = ServerName::try_from(host.clone()).map_err(|e| e.to_string())?;
+    let conn = ClientConnection::new(Arc::new(tls), name)?;
+    let mut stream = StreamOwned::new(conn, TcpStream::connect((host.as_str(), port))?);
+    // Complete the handshake, and so check the certificate, before the token
+    // is written
+    while stream.conn.is_handshaking() {
+        stream.conn.complete_io(&mut stream.sock)?;
+    }
+    stream.write_all(request.as_bytes())?;
+    stream.write_all(b"\r\n")?;
+    stream.write_all(body)?;
+    stream.flush()?;
+    let mut response = String::new();
+    BufReader::new(&mut stream).read_line(&mut response)?;
+    if response.starts_with('2') || response.starts_with('3') {
+        Ok(())
+    } else {
+        Err(format!(
+            "{} {path}: {}",
+            gettext("Upload failed"),
+            response.trim_end()
+        )
+        .into())
+    }
+}
+
+#[derive(Debug)]
+/// Accepts only a server certificate with the pinned SHA-256 fingerprint.
+/// Gemini servers commonly use self signed certificates, which cannot be
+/// verified against a certificate authority, so when no fingerprint is pinned
+/// the first certificate seen is trusted and pinned.
+struct Pinned {
+    /// The fingerprint which the certificate must match
+    pin: Arc<Mutex<Option<String>>>,
+    /// The algorithms used to check the handshake signature
+    algorithms: WebPkiSupportedAlgorithms,
+}
+
+impl Pinned {
+    fn new(pin: Arc<Mutex<Option<String>>>) -> Self {
+        Self {
+            pin,
+            algorithms: crypto::ring::default_provider().signature_verification_algorithms,
+        }
+    }
+}
+
+impl ServerCertVerifier for Pinned {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let seen = sha256(end_entity);
+        let mut pin = self.pin.lock().unwrap_or_else(PoisonError::into_inner);
+        match pin.as_deref() {
+            Some(p) if p != seen => Err(rustls::Error::General(format!(
+                "{}: {seen}",
+                gettext("Server certificate does not match the pinned fingerprint")
+            ))),
+            Some(_) => Ok(ServerCertVerified::assertion()),
+            None => {
+                *pin = Some(seen);
+                Ok(ServerCertVerified::assertion())
+            }
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls12_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls13_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.algorithms.supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        rustls::{
+            pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer},
+            ServerConfig, ServerConnection,
+        },
+        std::{io::Read, net::TcpListener, thread},
+    };
+
+    /// Returns an empty directory for a single test
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("zond-test-{}-{name}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn manifest(files: &[(&str, &str)]) -> Manifest {
+        files
+            .iter()
+            .map(|(p, h)| ((*p).to_string(), (*h).to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn manifest_diff() {
+        let target = Target::Directory {
+            path: PathBuf::from("unused"),
+            delete: true,
+        };
+        let mut deploy = Deploy {
+            name: "test",
+            target: &target,
+            root: PathBuf::new(),
+            manifest: PathBuf::new(),
+            previous: manifest(&[("same", "1"), ("edited", "2"), ("gone", "3")]),
+            current: manifest(&[("same", "1"), ("edited", "4"), ("new", "5")]),
+            pin: Arc::default(),
+        };
+        let changed: Vec<_> = deploy.changed().collect();
+        assert_eq!(changed, ["edited", "new"]);
+        let stale: Vec<_> = deploy.stale().collect();
+        assert_eq!(stale, ["gone"]);
+        let keep = Target::Directory {
+            path: PathBuf::from("unused"),
+            delete: false,
+        };
+        deploy.target = &keep;
+        assert_eq!(deploy.stale().count(), 0);
+    }
+
+    #[test]
+    fn directory_deploy_and_delete() {
+        let dir = scratch("directory");
+        let root = dir.join("public");
+        let dest = dir.join("dest");
+        let file = dir.join("manifest.ron");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("index.gmi"), "# Home").unwrap();
+        fs::write(root.join("sub").join("page.gmi"), "# Page").unwrap();
+        let target = Target::Directory {
+            path: dest.clone(),
+            delete: true,
+        };
+        Deploy::new("test", &target, root.clone(), file.clone())
+            .unwrap()
+            .run()
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(dest.join("index.gmi")).unwrap(),
+            "# Home"
+        );
+        assert_eq!(
+            fs::read_to_string(dest.join("sub").join("page.gmi")).unwrap(),
+            "# Page"
+        );
+        fs::write(root.join("index.gmi"), "# New home").unwrap();
+        fs::remove_file(root.join("sub").join("page.gmi")).unwrap();
+        let deploy = Deploy::new("test", &target, root.clone(), file.clone()).unwrap();
+        assert_eq!(deploy.changed().collect::<Vec<_>>(), ["index.gmi"]);
+        deploy.run().unwrap();
+        assert_eq!(
+            fs::read_to_string(dest.join("index.gmi")).unwrap(),
+            "# New home"
+        );
+        assert!(!dest.join("sub").join("page.gmi").exists());
+        let saved: Manifest = ron::de::from_str(&fs::read_to_string(&file).unwrap()).unwrap();
+        assert_eq!(saved.keys().collect::<Vec<_>>(), ["index.gmi"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn command_paths_are_quoted() {
+        assert_eq!(
+            expand("cp {local} /srv/{path}", "/a b/x", "it's {path}"),
+            r"cp '/a b/x' /srv/'it'\''s {path}'"
+        );
+        let root = scratch("command");
+        let path = "x$(touch pwned)'.gmi";
+        fs::write(root.join(path), "").unwrap();
+        command("test -f {path}", &root, path).unwrap();
+        assert!(!root.join("pwned").exists());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn titan_request_framing() {
+        let (url, line) = request(
+            "titan://example.org/capsule/",
+            "sub dir/page.gmi",
+            Some("secret"),
+            12,
+            "text/gemini",
+        )
+        .unwrap();
+        assert_eq!(
+            url.as_str(),
+            "titan://example.org/capsule/sub%20dir/page.gmi"
+        );
+        assert_eq!(
+            line,
+            "titan://example.org/capsule/sub%20dir/page.gmi;mime=text/gemini;size=12;token=secret"
+        );
+        let (_, line) = request("titan://example.org/", "a.gmi", None, 0, "text/plain").unwrap();
+        assert_eq!(line, "titan://example.org/a.gmi;mime=text/plain;size=0");
+    }
+
+    /// Starts a Titan server which accepts a single upload, returning it's
+    /// port, the fingerprint of it's certificate and the upload it received
+    fn server() -> (u16, String, thread::JoinHandle<Option<Vec<u8>>>) {
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let fingerprint = sha256(cert.cert.der());
+        let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(cert.key_pair.serialize_der()));
+        let tls = ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(vec![cert.cert.der().clone()], key)
+            .unwrap();
+        let listener = TcpListener::bind(("localhost", 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = thread::spawn(move || {
+            let (sock, _) = listener.accept().ok()?;
+            let conn = ServerConnection::new(Arc::new(tls)).ok()?;
+            let mut stream = StreamOwned::new(conn, sock);
+            let mut upload = Vec::new();
+            let mut reader = BufReader::new(&mut stream);
+            reader.read_until(b'\n', &mut upload).ok()?;
+            let mut body = [0; 5];
+            reader.read_exact(&mut body).ok()?;
+            upload.extend_from_slice(&body);
+            stream.write_all(b"20 text/gemini\r\n").ok()?;
+            stream.conn.send_close_notify();
+            stream.flush().ok()?;
+            Some(upload)
+        });
+        (port, fingerprint, handle)
+    }
+
+    #[test]
+    fn titan_upload_pins_certificate() {
+        let (port, fingerprint, handle) = server();
+        let pin = Arc::default();
+        let base = format!("titan://localhost:{port}/");
+        titan(&base, "a.gmi", Some("t"), b"hello", "text/gemini", &pin).unwrap();
+        let upload = handle.join().unwrap().unwrap();
+        assert_eq!(
+            String::from_utf8(upload).unwrap(),
+            format!("{base}a.gmi;mime=text/gemini;size=5;token=t\r\nhello")
+        );
+        assert_eq!(pin.lock().unwrap().as_deref(), Some(fingerprint.as_str()));
+    }
+
+    #[test]
+    fn titan_refuses_other_certificate() {
+        let (port, _, handle) = server();
+        let pin = Arc::new(Mutex::new(Some("00".repeat(32))));
+        let base = format!("titan://localhost:{port}/");
+        assert!(titan(&base, "a.gmi", Some("t"), b"hello", "text/gemini", &pin).is_err());
+        assert!(handle.join().unwrap().is_none());
+    }
+}
@@ -80,6 +80,8 @@ pub fn run(matches: &ArgMatches) -> Result<(), crate::Error> {
     idx_page.to_disk(&idx)?;
     idx = ["content", "gemlog", "index.gmi"].iter().collect();
     idx_page = Page::default();
+    // The gemlog index identifies the gemlog's feed
+    idx_page.meta.id = Some(Meta::new_id(&cfg.domain, &idx));
     idx_page.to_disk(&idx)?;
     Ok(())
 }
@@ -1,11 +1,17 @@
 /// Builds the capsule from the provided "content" directory
 pub mod build;
+/// Uploads the generated capsule
+pub mod deploy;
 /// Initializes a new capsule
 pub mod init;
 /// Standalone page operations
 pub mod page;
 /// Gemlog post operations
 pub mod post;
+/// Serves a preview of the capsule
+pub mod serve;
+/// Generates configuration for Gemini servers
+pub mod server_config;
 /// Tinylog ops
 pub mod tinylog;
 
@@ -21,6 +27,9 @@ pub fn run(matches: &ArgMatches) -> Result<(), crate::Error> {
         Some(("post", post_matches)) => post::run(post_matches)?,
         Some(("build", build_matches)) => build::run(build_matches)?,
         Some(("tinylog", tl_matches)) => tinylog::run(tl_matches)?,
+        Some(("server-config", sc_matches)) => server_config::run(sc_matches)?,
+        Some(("serve", serve_matches)) => serve::run(serve_matches)?,
+        Some(("deploy", deploy_matches)) => deploy::run(deploy_matches)?,
         _ => {}
     }
     Ok(())
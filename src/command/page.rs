@@ -13,6 +13,9 @@ pub fn run(matches: &ArgMatches) -> Result<(), crate::Error> {
         .get_one::<String>("title")
         .map_or("", std::string::String::as_str);
     let path = matches.get_one::<String>("path").map(PathBuf::from);
+    let slug = matches.get_one::<String>("slug").map(String::as_str);
+    // The name the page's file is found by, if no path was given
+    let name = slug.unwrap_or(title);
     match matches.subcommand() {
         Some(("init", init_matches)) => {
             let tags = match init_matches.get_many::<String>("tags") {
@@ -22,21 +25,22 @@ pub fn run(matches: &ArgMatches) -> Result<(), crate::Error> {
             Page::create(
                 Kind::Page(path.clone()),
                 title,
+                slug,
                 init_matches.get_one::<String>("summary").map(|x| &**x),
                 tags,
             )?;
             if init_matches.get_flag("edit") || init_matches.get_flag("publish") {
-                Page::edit(Kind::Page(path.clone()), title)?;
+                Page::edit(Kind::Page(path.clone()), name)?;
             }
             if init_matches.get_flag("publish") {
-                Page::publish(Kind::Page(path), title)?;
+                Page::publish(Kind::Page(path), name)?;
             }
         }
         Some(("publish", _publish_matches)) => {
-            Page::publish(Kind::Page(path), title)?;
+            Page::publish(Kind::Page(path), name)?;
         }
         Some(("edit", _edit_matches)) => {
-            Page::edit(Kind::Page(path), title)?;
+            Page::edit(Kind::Page(path), name)?;
         }
         _ => {}
     }
@@ -1,5 +1,8 @@
 use {
-    crate::content::{Kind, Page},
+    crate::{
+        config::GEMLOG,
+        content::{Kind, Page},
+    },
     clap::ArgMatches,
     gettextrs::gettext,
     std::string::ToString,
@@ -12,6 +15,10 @@ pub fn run(matches: &ArgMatches) -> Result<(), crate::Error> {
     let Some(title) = matches.get_one::<String>("title") else {
         return Err(gettext("Missing title").into());
     };
+    let slug = matches.get_one::<String>("slug").map(String::as_str);
+    // The name the post's file is found by
+    let name = slug.unwrap_or(title);
+    let kind = Kind::Post(collection(matches.get_one::<String>("collection"))?);
     match matches.subcommand() {
         Some(("init", init_matches)) => {
             let tags = match init_matches.get_many::<String>("tags") {
@@ -19,25 +26,39 @@ pub fn run(matches: &ArgMatches) -> Result<(), crate::Error> {
                 None => Vec::new(),
             };
             Page::create(
-                Kind::Post,
+                kind.clone(),
                 title,
+                slug,
                 init_matches.get_one::<String>("summary").map(|x| &**x),
                 tags,
             )?;
             if init_matches.get_flag("edit") || init_matches.get_flag("publish") {
-                Page::edit(Kind::Post, title)?;
+                Page::edit(kind.clone(), name)?;
             }
             if init_matches.get_flag("publish") {
-                Page::publish(Kind::Post, title)?;
+                Page::publish(kind, name)?;
             }
         }
         Some(("publish", _publish_matches)) => {
-            Page::publish(Kind::Post, title)?;
+            Page::publish(kind, name)?;
         }
         Some(("edit", _edit_matches)) => {
-            Page::edit(Kind::Post, title)?;
+            Page::edit(kind, name)?;
         }
         _ => {}
     }
     Ok(())
 }
+
+/// Returns the collection named on the command line, or the gemlog if none was
+/// given. When the gemlog is not among the configured collections, the first
+/// one is used instead.
+fn collection(name: Option<&String>) -> Result<String, crate::Error> {
+    let collections = crate::load_config().collections();
+    match name {
+        Some(n) if collections.contains_key(n) => Ok(n.clone()),
+        Some(n) => Err(format!("{}: {n}", gettext("Unknown collection")).into()),
+        None if collections.contains_key(GEMLOG) => Ok(GEMLOG.to_string()),
+        None => Ok(collections.into_keys().next().unwrap_or_default()),
+    }
+}
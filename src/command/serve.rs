@@ -0,0 +1,253 @@
+use {
+    super::build,
+    clap::ArgMatches,
+    gettextrs::gettext,
+    percent_encoding::percent_decode_str,
+    rustls::{
+        pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer},
+        ServerConfig, ServerConnection, StreamOwned,
+    },
+    std::{
+        env, fs,
+        io::{self, BufRead, BufReader, Read, Write},
+        net::{TcpListener, TcpStream},
+        path::{Path, PathBuf},
+        process,
+        sync::Arc,
+        thread,
+    },
+    tinyrand::{Rand, Seeded, StdRand},
+    tinyrand_std::clock_seed::ClockSeed,
+    url::Url,
+};
+
+#[cfg(unix)]
+use std::os::unix::fs::DirBuilderExt;
+
+/// The longest request allowed by the Gemini specification, including the
+/// terminating CRLF
+const MAX_REQUEST: usize = 1026;
+
+/// Builds the capsule into a temporary directory and serves it on localhost
+/// # Errors
+/// Errors are bubbled up from the called functions
+pub fn run(matches: &ArgMatches) -> Result<(), crate::Error> {
+    let port = matches.get_one::<u16>("port").copied().unwrap_or(1965);
+    let dir = private_dir()?;
+    // The build replaces it's output directory, so it goes inside the private
+    // directory rather than being the private directory itself
+    let root = dir.join("capsule");
+    // The preview normally runs until it is stopped with Ctrl-C, so the build
+    // is removed there as well as when serving fails
+    let build = dir.clone();
+    ctrlc::set_handler(move || {
+        let _ = fs::remove_dir_all(&build);
+        process::exit(0);
+    })
+    .map_err(|e| e.to_string())?;
+    let result = serve(&root, port, matches.get_flag("watch"));
+    let _ = fs::remove_dir_all(&dir);
+    result
+}
+
+/// Builds the capsule into `root` and serves it, rebuilding on changes if
+/// `watch` is set
+fn serve(root: &Path, port: u16, watch: bool) -> Result<(), crate::Error> {
+    // Bound before building, so that a port which is in use is reported
+    // straight away rather than from the server's thread
+    let listener = TcpListener::bind(("localhost", port))?;
+    build::build(root)?;
+    // The build is written below `Config.path`, which is served as the root
+    let mut capsule = root.to_path_buf();
+    if let Some(p) = &crate::load_config().path {
+        capsule.push(p);
+    }
+    let server = Server::new(capsule, port)?;
+    println!(
+        "{} gemini://localhost:{port}/",
+        gettext("Serving capsule preview at")
+    );
+    if watch {
+        let server = thread::spawn(move || server.listen(&listener));
+        build::watch(root)?;
+        return server
+            .join()
+            .map_err(|_| gettext("Preview server stopped").into());
+    }
+    server.listen(&listener);
+    Ok(())
+}
+
+/// Creates a new temporary directory, with a random name and only accessible
+/// to the current user, to build the preview into. The directory must not
+/// already exist, so that nothing planted at a predictable path is followed.
+fn private_dir() -> Result<PathBuf, crate::Error> {
+    let mut rand = StdRand::seed(ClockSeed.next_u64());
+    let mut builder = fs::DirBuilder::new();
+    #[cfg(unix)]
+    builder.mode(0o700);
+    for _ in 0..16 {
+        let dir = env::temp_dir().join(format!("zond-preview-{:016x}", rand.next_u64()));
+        match builder.create(&dir) {
+            Ok(()) => return Ok(dir),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Err(gettext("Unable to create a temporary directory").into())
+}
+
+/// A minimal Gemini server for previewing the capsule
+pub struct Server {
+    /// The directory holding the generated capsule
+    root: PathBuf,
+    /// The port to listen on
+    port: u16,
+    /// The TLS configuration, using a self signed certificate
+    tls: Arc<ServerConfig>,
+}
+
+impl Server {
+    /// Creates a server for the capsule in `root`, generating a self signed
+    /// certificate for localhost
+    /// # Errors
+    /// Returns an error if the certificate cannot be generated
+    pub fn new(root: PathBuf, port: u16) -> Result<Self, crate::Error> {
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()])?;
+        let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(cert.key_pair.serialize_der()));
+        let tls = ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(vec![cert.cert.der().clone()], key)?;
+        Ok(Self {
+            root,
+            port,
+            tls: Arc::new(tls),
+        })
+    }
+
+    /// Accepts connections on `listener` until the process is killed, handling
+    /// each on it's own thread
+    pub fn listen(self, listener: &TcpListener) {
+        let server = Arc::new(self);
+        for stream in listener.incoming().flatten() {
+            let server = Arc::clone(&server);
+            thread::spawn(move || {
+                if let Err(e) = server.handle(stream) {
+                    eprintln!("{}: {e}", gettext("Error handling request"));
+                }
+            });
+        }
+    }
+
+    /// Reads a single request and writes the response
+    fn handle(&self, stream: TcpStream) -> Result<(), crate::Error> {
+        let conn = ServerConnection::new(Arc::clone(&self.tls))?;
+        let mut stream = StreamOwned::new(conn, stream);
+        let mut request = Vec::new();
+        BufReader::new((&mut stream).take(MAX_REQUEST as u64)).read_until(b'\n', &mut request)?;
+        let request = String::from_utf8_lossy(&request);
+        let (header, body) = match Url::parse(request.trim_end()) {
+            Ok(url) if url.scheme() == "gemini" => self.respond(&url),
+            _ => ("59 Bad request".to_string(), None),
+        };
+        println!("{} {header}", request.trim_end());
+        stream.write_all(header.as_bytes())?;
+        stream.write_all(b"\r\n")?;
+        if let Some(body) = body {
+            stream.write_all(&body)?;
+        }
+        stream.conn.send_close_notify();
+        stream.flush()?;
+        Ok(())
+    }
+
+    /// Returns the response header and body for a request
+    fn respond(&self, url: &Url) -> (String, Option<Vec<u8>>) {
+        let path = percent_decode_str(url.path()).decode_utf8_lossy();
+        let mut file = self.root.clone();
+        for segment in path.split('/').filter(|s| !s.is_empty() && *s != "..") {
+            file.push(segment);
+        }
+        if file.is_dir() {
+            if !path.ends_with('/') {
+                return (format!("31 {}/", url.path()), None);
+            }
+            file.push("index.gmi");
+        }
+        match fs::read(&file) {
+            Ok(body) => {
+                let mime = mime_type(&file);
+                let body = if mime.starts_with("text/") || mime.ends_with("xml") {
+                    self.rewrite(&String::from_utf8_lossy(&body)).into_bytes()
+                } else {
+                    body
+                };
+                (format!("20 {mime}"), Some(body))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => ("51 Not found".to_string(), None),
+            Err(_) => ("40 Error reading file".to_string(), None),
+        }
+    }
+
+    /// Replaces absolute links to the capsule's url, including any path set in
+    /// `Config.ron`, with links to the preview server
+    fn rewrite(&self, text: &str) -> String {
+        let from = match crate::load_config().url() {
+            Ok(url) => url.as_str().trim_end_matches('/').to_string(),
+            Err(_) => return text.to_string(),
+        };
+        let to = format!("gemini://localhost:{}", self.port);
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(idx) = rest.find(&from) {
+            let after = &rest[idx + from.len()..];
+            out.push_str(&rest[..idx]);
+            match after.chars().next() {
+                Some(c) if c.is_alphanumeric() || ".-:".contains(c) => out.push_str(&from),
+                _ => out.push_str(&to),
+            }
+            rest = after;
+        }
+        out.push_str(rest);
+        out
+    }
+}
+
+/// Guesses the mime type of a file from it's extension
+pub(crate) fn mime_type(file: &Path) -> &'static str {
+    let name = file
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default();
+    let ext = file
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default();
+    match ext.to_lowercase().as_str() {
+        "gmi" | "gemini" => "text/gemini",
+        "txt" => "text/plain",
+        "md" => "text/markdown",
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "csv" => "text/csv",
+        "xml" if name.starts_with("atom") => "application/atom+xml",
+        "xml" => "application/xml",
+        "json" => "application/json",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "ico" => "image/vnd.microsoft.icon",
+        "mp3" => "audio/mpeg",
+        "ogg" | "oga" => "audio/ogg",
+        "flac" => "audio/flac",
+        "wav" => "audio/wav",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        _ => "application/octet-stream",
+    }
+}
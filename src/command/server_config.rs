@@ -0,0 +1,362 @@
+use {
+    crate::content::{lang, permalink, Page},
+    clap::ArgMatches,
+    gettextrs::gettext,
+    std::{
+        collections::{BTreeMap, BTreeSet},
+        fmt::Write,
+        fs,
+        path::{Path, PathBuf},
+    },
+    walkdir::WalkDir,
+};
+
+/// The mime type which Atom feeds are served as
+const ATOM: &str = "application/atom+xml";
+
+/// Generates configuration for one of the supported Gemini servers
+/// # Errors
+/// Errors are bubbled up from the called functions
+pub fn run(matches: &ArgMatches) -> Result<(), crate::Error> {
+    let output = PathBuf::from(
+        matches
+            .get_one::<String>("output")
+            .map_or("public", String::as_str),
+    );
+    let site = Site::load()?;
+    match matches.get_one::<String>("server").map(String::as_str) {
+        Some("agate") => site.write_agate(&output)?,
+        Some("molly-brown") => print!("{}", site.molly_brown(&output)?),
+        Some("jetforce") => print!("{}", site.jetforce(&output)?),
+        Some("gmid") => print!("{}", site.gmid()?),
+        _ => {}
+    }
+    Ok(())
+}
+
+/// The facts about a capsule which are relevant to serving it
+struct Site {
+    /// Every language other than the default which is used in the capsule
+    langs: BTreeSet<String>,
+    /// Pages whose language is set in their metadata rather than by their file
+    /// name, as paths relative to the capsule root
+    pages: BTreeMap<PathBuf, String>,
+    /// Paths which have moved, as a map from the old path to the new one. This
+    /// holds the redirects from `Config.ron` and the aliases of every page.
+    redirects: BTreeMap<String, String>,
+}
+
+impl Site {
+    /// Walks the content directory, recording the language and aliases of each
+    /// page
+    fn load() -> Result<Self, crate::Error> {
+        let cfg = crate::load_config();
+        let mut langs = BTreeSet::new();
+        let mut pages = BTreeMap::new();
+        let mut redirects = cfg.server.redirects.clone();
+        for entry in WalkDir::new("content").into_iter().flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("gmi") {
+                continue;
+            }
+            let Some(page) = Page::from_path(path) else {
+                continue;
+            };
+            let path = path.strip_prefix("content")?;
+            let collection = cfg.collection(path);
+            let permalink = permalink::path(path, &page.meta, collection.as_deref());
+            if page.meta.published.is_some() {
+                for alias in &page.meta.aliases {
+                    redirects.insert(
+                        permalink::alias(alias).to_string_lossy().to_string(),
+                        permalink.to_string_lossy().to_string(),
+                    );
+                }
+            }
+            let Some(l) = lang::resolve(path, Some(&page.meta)) else {
+                continue;
+            };
+            if lang::is_default(Some(&l)) {
+                continue;
+            }
+            if lang::resolve(path, None).as_deref() != Some(l.as_str()) {
+                pages.insert(permalink, l.clone());
+            }
+            langs.insert(l);
+        }
+        Ok(Self {
+            langs,
+            pages,
+            redirects,
+        })
+    }
+
+    /// Writes a `.meta` file for Agate into each directory of the generated
+    /// capsule
+    fn write_agate(&self, output: &Path) -> Result<(), crate::Error> {
+        let cfg = crate::load_config();
+        if !output.exists() {
+            return Err(format!(
+                "{}: {}",
+                gettext("Capsule has not been built"),
+                output.display()
+            )
+            .into());
+        }
+        if !cfg.server.protected.is_empty() {
+            eprintln!(
+                "{}",
+                gettext("Agate does not support client certificates, protected paths are ignored")
+            );
+        }
+        for entry in WalkDir::new(output).into_iter().flatten() {
+            if !entry.file_type().is_dir() {
+                continue;
+            }
+            let dir = entry.path().strip_prefix(output)?;
+            let mut meta = String::new();
+            for (from, to) in &self.redirects {
+                let from = Path::new(from);
+                if from.parent().unwrap_or(Path::new("")) == dir {
+                    if let Some(name) = from.file_name() {
+                        let url = cfg.url()?.join(&format!("{}{to}", prefix()))?;
+                        writeln!(meta, "{}: 31 {url}", name.to_string_lossy())?;
+                    }
+                }
+            }
+            for (path, l) in &self.pages {
+                if path.parent().unwrap_or(Path::new("")) == dir {
+                    if let Some(name) = path.file_name() {
+                        writeln!(meta, "{}: ;lang={l}", name.to_string_lossy())?;
+                    }
+                }
+            }
+            for file in fs::read_dir(entry.path())?.flatten() {
+                let name = file.file_name();
+                let name = name.to_string_lossy();
+                if name.starts_with("atom") && name.ends_with(".xml") {
+                    writeln!(meta, "{name}: {ATOM}")?;
+                }
+            }
+            for l in &self.langs {
+                writeln!(meta, "*.{l}.gmi: ;lang={l}")?;
+            }
+            if let Some(l) = &cfg.lang {
+                writeln!(meta, "*.gmi: ;lang={l}")?;
+            }
+            if !meta.is_empty() {
+                fs::write(entry.path().join(".meta"), meta)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Generates a configuration file for Molly Brown
+    fn molly_brown(&self, output: &Path) -> Result<String, crate::Error> {
+        let cfg = crate::load_config();
+        let prefix = prefix();
+        let mut conf = format!(
+            "# {}\nHostname = '{}'\nDocBase = '{}'\n",
+            gettext("Generated by zond"),
+            cfg.domain,
+            root(output)
+        );
+        if let Some(l) = &cfg.lang {
+            writeln!(conf, "DefaultLang = '{l}'")?;
+        }
+        writeln!(conf, "\n[MimeOverrides]")?;
+        for (path, l) in &self.pages {
+            let path = escape(&format!("{prefix}{}", path.display()));
+            writeln!(conf, "'^{path}$' = 'text/gemini; lang={l}'")?;
+        }
+        for l in &self.langs {
+            writeln!(conf, "'\\.{}\\.gmi$' = 'text/gemini; lang={l}'", escape(l))?;
+        }
+        writeln!(conf, "'/atom[^/]*\\.xml$' = '{ATOM}'")?;
+        if !self.redirects.is_empty() {
+            writeln!(conf, "\n[PermRedirects]")?;
+            for (from, to) in &self.redirects {
+                writeln!(
+                    conf,
+                    "'^{}$' = '{prefix}{to}'",
+                    escape(&format!("{prefix}{from}"))
+                )?;
+            }
+        }
+        if !cfg.server.protected.is_empty() {
+            writeln!(conf, "\n[CertificateZones]")?;
+            for (path, certs) in &cfg.server.protected {
+                let certs = certs
+                    .iter()
+                    .map(|c| format!("'{}'", c.to_lowercase().replace(':', "")))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                writeln!(
+                    conf,
+                    "'^{}' = [{certs}]",
+                    escape(&format!("{prefix}{path}"))
+                )?;
+            }
+        }
+        Ok(conf)
+    }
+
+    /// Generates a Python script which serves the capsule using Jetforce
+    fn jetforce(&self, output: &Path) -> Result<String, crate::Error> {
+        let cfg = crate::load_config();
+        let prefix = prefix();
+        let mut langs = String::new();
+        for (path, l) in &self.pages {
+            if let Some(name) = path.file_name() {
+                writeln!(langs, "    (\"{}\", \"{l}\"),", name.to_string_lossy())?;
+            }
+        }
+        for l in &self.langs {
+            writeln!(langs, "    (\"*.{l}.gmi\", \"{l}\"),")?;
+        }
+        if let Some(l) = &cfg.lang {
+            writeln!(langs, "    (\"*.gmi\", \"{l}\"),")?;
+        }
+        let mut script = format!(
+            "# {}\n\
+            from fnmatch import fnmatch\n\
+            from jetforce import GeminiServer, Response, Status, StaticDirectoryApplication\n\
+            \n\
+            ROOT = \"{}\"\n\
+            LANGS = [\n{langs}]\n\
+            \n\
+            \n\
+            class Capsule(StaticDirectoryApplication):\n    \
+                def guess_mimetype(self, filename):\n        \
+                    mime = super().guess_mimetype(filename)\n        \
+                    if mime.startswith(\"text/gemini\"):\n            \
+                        for pattern, lang in LANGS:\n                \
+                            if fnmatch(filename, pattern):\n                    \
+                                return f\"text/gemini; lang={{lang}}\"\n        \
+                    return mime\n\
+            \n\
+            \n\
+            app = Capsule(root_directory=ROOT)\n\
+            app.mimetypes.add_type(\"{ATOM}\", \".xml\")\n",
+            gettext("Generated by zond"),
+            root(output)
+        );
+        for (n, (from, to)) in self.redirects.iter().enumerate() {
+            write!(
+                script,
+                "\n\n@app.route(r\"{}\")\n\
+                def redirect_{n}(request):\n    \
+                    return Response(Status.REDIRECT_PERMANENT, \"{prefix}{to}\")\n",
+                escape(&format!("{prefix}{from}"))
+            )?;
+        }
+        for (n, (path, certs)) in cfg.server.protected.iter().enumerate() {
+            let certs = certs
+                .iter()
+                .map(|c| format!("\"{}\"", c.to_uppercase().replace(':', "")))
+                .collect::<Vec<_>>()
+                .join(", ");
+            write!(
+                script,
+                "\n\n@app.route(r\"{}.*\")\n\
+                def protected_{n}(request):\n    \
+                    cert = request.environ.get(\"TLS_CLIENT_HASH\") or \"\"\n    \
+                    if cert.removeprefix(\"SHA256:\").replace(\":\", \"\").upper() not in [{certs}]:\n        \
+                        return Response(Status.CLIENT_CERTIFICATE_REQUIRED, \"{}\")\n    \
+                    return app.serve_static_file(request)\n",
+                escape(&format!("{prefix}{path}")),
+                gettext("Certificate required")
+            )?;
+        }
+        write!(
+            script,
+            "\n\nif __name__ == \"__main__\":\n    \
+                GeminiServer(app, hostname=\"{}\").run()\n",
+            cfg.domain
+        )?;
+        Ok(script)
+    }
+
+    /// Generates `location` blocks to be placed inside of the capsule's
+    /// `server` block in gmid's configuration
+    fn gmid(&self) -> Result<String, crate::Error> {
+        let cfg = crate::load_config();
+        let prefix = prefix();
+        let mut conf = format!("# {}\n", gettext("Generated by zond"));
+        for (from, to) in &self.redirects {
+            writeln!(
+                conf,
+                "location \"{prefix}{from}\" {{\n\tblock return 31 \"{prefix}{to}\"\n}}"
+            )?;
+        }
+        for path in cfg.server.protected.keys() {
+            if let Some(ca) = &cfg.server.client_ca {
+                writeln!(
+                    conf,
+                    "location \"{prefix}{path}*\" {{\n\trequire client ca \"{ca}\"\n}}"
+                )?;
+            } else {
+                eprintln!(
+                    "{}: {path}",
+                    gettext("gmid requires `client_ca` to protect a path")
+                );
+            }
+        }
+        for (path, l) in &self.pages {
+            writeln!(
+                conf,
+                "location \"{prefix}{}\" {{\n\tlang \"{l}\"\n}}",
+                path.display()
+            )?;
+        }
+        for l in &self.langs {
+            writeln!(
+                conf,
+                "location \"{prefix}*.{l}.gmi\" {{\n\tlang \"{l}\"\n}}"
+            )?;
+        }
+        writeln!(
+            conf,
+            "location \"{prefix}*atom*.xml\" {{\n\tdefault type \"{ATOM}\"\n}}"
+        )?;
+        if let Some(l) = &cfg.lang {
+            writeln!(conf, "location \"{prefix}*\" {{\n\tlang \"{l}\"\n}}")?;
+        }
+        Ok(conf)
+    }
+}
+
+/// Returns the url path of the capsule root, with leading and trailing slashes
+fn prefix() -> String {
+    match crate::load_config()
+        .path
+        .as_deref()
+        .map(|p| p.trim_matches('/'))
+    {
+        Some(p) if !p.is_empty() => format!("/{p}/"),
+        _ => "/".to_string(),
+    }
+}
+
+/// Returns the directory the capsule is served from
+fn root(output: &Path) -> String {
+    match &crate::load_config().server.root {
+        Some(r) => r.clone(),
+        None => fs::canonicalize(output)
+            .unwrap_or_else(|_| output.to_path_buf())
+            .display()
+            .to_string(),
+    }
+}
+
+/// Escapes the characters which have a special meaning in a regular expression
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if "\\.+*?()|[]{}^$".contains(c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
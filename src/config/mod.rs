@@ -5,20 +5,25 @@ pub use license::License;
 
 use crate::Error;
 use {
-    crate::link::Link,
+    crate::{link::Link, template::Templates},
     atom_syndication as atom,
     gettextrs::gettext,
     ron::ser::{to_writer_pretty, PrettyConfig},
     serde::{Deserialize, Serialize},
     std::{
+        collections::{BTreeMap, HashMap},
         fs::{self, File},
         io::BufWriter,
-        path::PathBuf,
+        path::{Path, PathBuf},
         str::FromStr,
     },
     url::Url,
 };
 
+/// The collection which holds the gemlog, and which is used when no others are
+/// configured
+pub const GEMLOG: &str = "gemlog";
+
 #[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
 /// The type of feed to generate
 pub enum Feed {
@@ -78,6 +83,198 @@ impl FromStr for DisplayDate {
     }
 }
 
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default)]
+/// Ascii art banner settings
+pub struct Banner {
+    /// Alt text placed after the opening fence of the preformatted block, for
+    /// the benefit of screen readers
+    pub alt: Option<String>,
+    /// A set of banner files, relative to the capsule root, from which one will
+    /// be picked for each page. The same page always gets the same banner.
+    pub rotate: Vec<PathBuf>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+/// The order in which pages are listed in generated directory indexes
+pub enum SortBy {
+    /// Alphabetically by title
+    #[default]
+    Title,
+    /// By date of publication, newest first
+    Date,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default)]
+/// Settings for the index pages generated for content subdirectories
+pub struct AutoIndex {
+    /// Whether to generate an index for directories which do not have one
+    pub enabled: bool,
+    /// The order in which pages are listed
+    pub sort: SortBy,
+}
+
+impl Default for AutoIndex {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            sort: SortBy::default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default)]
+/// Facts about how the capsule is served, used to generate configuration for
+/// Gemini servers
+pub struct Server {
+    /// The directory the capsule is served from on the server. If not set, the
+    /// location of the generated capsule is used.
+    pub root: Option<String>,
+    /// Pages which have moved, as a map from the old path to the new one. Both
+    /// paths are relative to the capsule root.
+    pub redirects: BTreeMap<String, String>,
+    /// Directories, relative to the capsule root, which may only be accessed
+    /// using one of the listed client certificates. Certificates are given by
+    /// their SHA-256 fingerprint.
+    pub protected: BTreeMap<String, Vec<String>>,
+    /// The certificate authority used to verify client certificates, for
+    /// servers which do not check fingerprints
+    pub client_ca: Option<String>,
+    /// Whether to write a page at each alias of a page linking to it's new
+    /// location, for servers which have not been configured with the redirects
+    pub stubs: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default)]
+/// Processing applied to JPEG and PNG images copied from the content directory
+pub struct Images {
+    /// Whether to remove metadata such as EXIF, which may include the location
+    /// a photo was taken at
+    pub strip: bool,
+    /// If set, images larger than this many pixels in either dimension are
+    /// scaled down to fit
+    pub max_size: Option<u32>,
+    /// Whether to print what was changed in each image
+    pub report: bool,
+}
+
+impl Default for Images {
+    fn default() -> Self {
+        Self {
+            strip: true,
+            max_size: None,
+            report: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default)]
+/// Notes appended to link lines, warning readers about large files and links
+/// which leave Gemini
+pub struct Annotations {
+    /// Whether link lines in page content are annotated
+    pub enabled: bool,
+    /// The text appended to links pointing to files copied from the content
+    /// directory. `{type}` is replaced with the kind of file and `{size}` with
+    /// it's size. An empty string turns these annotations off.
+    pub asset: String,
+    /// The text appended to links using a protocol other than Gemini. `{scheme}`
+    /// is replaced with the protocol. An empty string turns these annotations
+    /// off.
+    pub protocol: String,
+}
+
+impl Default for Annotations {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            asset: "({type}, {size})".to_string(),
+            protocol: "[{scheme}]".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default)]
+/// A directory of dated entries, such as the gemlog, which gets it's own index
+/// and feeds
+pub struct Collection {
+    /// The heading for the list of entries. Defaults to the name of the
+    /// directory, or "Gemlog posts" for the gemlog.
+    pub title: Option<String>,
+    /// The number of entries to display on the main index. Defaults to
+    /// `entries`.
+    pub entries: Option<usize>,
+    /// Whether to generate atom and/or gemini feeds. Defaults to `feed`.
+    pub feed: Option<Feed>,
+    /// Where new entries are placed within the collection's directory, such
+    /// as `{year}/{month}/{slug}`. The date placeholders are filled in with the
+    /// date the entry is created. Defaults to `{slug}`.
+    pub layout: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default)]
+/// Patterns for the paths which published pages are written to, so that urls
+/// do not depend on where the source files are kept. `{year}`, `{month}` and
+/// `{day}` are replaced with the date of publication, `{slug}` with the slug
+/// from the page's metadata or else it's file name, and `{section}` with the
+/// collection a post belongs to or the directory a page is in.
+pub struct Permalinks {
+    /// The pattern for ordinary pages, such as `{section}/{slug}`. If not set,
+    /// pages keep the path they have in the content directory.
+    pub page: Option<String>,
+    /// The pattern for entries in collections, such as
+    /// `{section}/{year}/{slug}`. If not set, posts keep the path they have in
+    /// the content directory.
+    pub post: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+/// A location which the generated capsule can be deployed to
+pub enum Target {
+    /// A directory on the local machine
+    Directory {
+        /// The directory to copy the capsule into
+        path: PathBuf,
+        /// Whether to delete files which are no longer part of the capsule
+        #[serde(default)]
+        delete: bool,
+    },
+    /// A command which is run once for each file, such as `rsync` or `scp`.
+    /// The placeholder `{local}` is replaced with the path to the file on the
+    /// local machine, and `{path}` with it's path from the capsule root. Both
+    /// are quoted for the shell, so they should not be quoted again.
+    Command {
+        /// The command used to upload a file
+        upload: String,
+        /// If set, the command used to delete files which are no longer part
+        /// of the capsule
+        #[serde(default)]
+        remove: Option<String>,
+    },
+    /// A server which accepts uploads using the Titan protocol
+    Titan {
+        /// The titan url corresponding to the capsule root, such as
+        /// `titan://example.org/`
+        url: String,
+        /// The token sent to the server with each upload, if it requires one
+        #[serde(default)]
+        token: Option<String>,
+        /// The SHA-256 fingerprint of the server's certificate. If not set,
+        /// the certificate seen on the first deploy is trusted from then on.
+        #[serde(default)]
+        fingerprint: Option<String>,
+        /// Whether to delete files which are no longer part of the capsule
+        #[serde(default)]
+        delete: bool,
+    },
+}
+
 #[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
 /// Site wide capsule settings
 pub struct Config {
@@ -89,6 +286,9 @@ pub struct Config {
     pub domain: String,
     /// The path from the server root to the capsule
     pub path: Option<String>,
+    /// The default language of the capsule's content, such as "en"
+    #[serde(default)]
+    pub lang: Option<String>,
     /// The number of gemlog entries to display on the main index
     pub entries: usize,
     /// Which pages to display the publication date for
@@ -101,6 +301,46 @@ pub struct Config {
     pub show_email: bool,
     /// A collection of links to display at the bottom of each page
     pub footer_links: Vec<Link>,
+    /// Ascii art banner settings
+    #[serde(default)]
+    pub banner: Banner,
+    /// Generated index pages for content subdirectories
+    #[serde(default)]
+    pub auto_index: AutoIndex,
+    /// If set, the word count and estimated reading time at this many words per
+    /// minute are displayed for each page and post
+    #[serde(default)]
+    pub reading_time: Option<usize>,
+    /// The default templates for each kind of page
+    #[serde(default)]
+    pub templates: Templates,
+    /// The language of the text which zond generates for the capsule, such as
+    /// navigation links and headings. Defaults to `lang`.
+    #[serde(default)]
+    pub language: Option<String>,
+    /// Replacements for individual generated strings, keyed by the original
+    /// English text
+    #[serde(default)]
+    pub strings: HashMap<String, String>,
+    /// Settings used when generating server configuration
+    #[serde(default)]
+    pub server: Server,
+    /// Named locations which the capsule can be deployed to
+    #[serde(default)]
+    pub deploy: BTreeMap<String, Target>,
+    /// Processing applied to images copied into the capsule
+    #[serde(default)]
+    pub images: Images,
+    /// Annotations added to link lines
+    #[serde(default)]
+    pub annotations: Annotations,
+    /// Directories of dated entries, keyed by their path within the content
+    /// directory. If empty, the gemlog is the only collection.
+    #[serde(default)]
+    pub collections: BTreeMap<String, Collection>,
+    /// Where published pages are written within the generated capsule
+    #[serde(default)]
+    pub permalinks: Permalinks,
 }
 
 impl Config {
@@ -135,6 +375,50 @@ impl Config {
         Ok(())
     }
 
+    /// Returns the collections of dated entries in the capsule, keyed by their
+    /// path within the content directory
+    pub fn collections(&self) -> BTreeMap<String, Collection> {
+        if self.collections.is_empty() {
+            BTreeMap::from([(GEMLOG.to_string(), Collection::default())])
+        } else {
+            self.collections.clone()
+        }
+    }
+
+    /// Returns the name of the collection which the file at `path`, relative
+    /// to the content directory, belongs to
+    pub fn collection(&self, path: &Path) -> Option<String> {
+        self.collections()
+            .into_keys()
+            .filter(|c| path.starts_with(c))
+            .max_by_key(String::len)
+    }
+
+    /// Returns the number of entries from a collection which are displayed on
+    /// the main index
+    pub fn entries(&self, collection: &str) -> usize {
+        self.collections()
+            .get(collection)
+            .and_then(|c| c.entries)
+            .unwrap_or(self.entries)
+    }
+
+    /// Returns the layout of new entries in a collection
+    pub fn layout(&self, collection: &str) -> String {
+        self.collections()
+            .remove(collection)
+            .and_then(|c| c.layout)
+            .unwrap_or_else(|| "{slug}".to_string())
+    }
+
+    /// Returns which feeds are generated for a collection
+    pub fn feed(&self, collection: &str) -> Option<Feed> {
+        self.collections()
+            .get(collection)
+            .and_then(|c| c.feed.clone())
+            .or_else(|| self.feed.clone())
+    }
+
     /// Returns the address for the root of this capsule
     pub fn url(&self) -> Result<Url, crate::Error> {
         let mut path = PathBuf::new();
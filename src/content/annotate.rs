@@ -0,0 +1,140 @@
+use {
+    crate::strings,
+    percent_encoding::percent_decode_str,
+    std::{
+        fmt::Write,
+        fs,
+        path::{Component, Path, PathBuf},
+    },
+    url::{ParseError, Url},
+};
+
+/// Appends the configured annotations to each link line in `content`, skipping
+/// any which appear inside of preformatted blocks. `page` is the path the page
+/// is being written to and `root` is the directory holding the generated
+/// capsule, which is used to look up the size of linked files.
+pub fn links(content: &str, page: &Path, root: &Path) -> Result<String, crate::Error> {
+    let cfg = &crate::load_config().annotations;
+    if !cfg.enabled {
+        return Ok(content.to_string());
+    }
+    let mut pre = false;
+    let mut out = String::with_capacity(content.len());
+    for line in content.lines() {
+        if line.starts_with("```") {
+            pre = !pre;
+        }
+        match line.strip_prefix("=>").filter(|_| !pre) {
+            Some(link) => {
+                let link = link.trim();
+                let (url, display) = link
+                    .split_once(char::is_whitespace)
+                    .map_or((link, ""), |(u, d)| (u, d.trim()));
+                match annotation(url, page, root) {
+                    // A link without any text is displayed as it's url
+                    Some(note) if display.is_empty() => writeln!(out, "=> {url} {url} {note}")?,
+                    Some(note) => writeln!(out, "=> {url} {display} {note}")?,
+                    None => writeln!(out, "{line}")?,
+                }
+            }
+            None => writeln!(out, "{line}")?,
+        }
+    }
+    if !content.ends_with('\n') {
+        out.pop();
+    }
+    Ok(out)
+}
+
+/// Returns the annotation for a single link, if it needs one
+fn annotation(url: &str, page: &Path, root: &Path) -> Option<String> {
+    let cfg = crate::load_config();
+    let fmt = &cfg.annotations;
+    let file = match Url::parse(url) {
+        Ok(u) if u.scheme() == "gemini" => {
+            if u.host_str() != Some(cfg.domain.as_str()) {
+                return None;
+            }
+            local(u.path(), page, root)?
+        }
+        Ok(_) if fmt.protocol.is_empty() => return None,
+        Ok(u) => return Some(fmt.protocol.replace("{scheme}", u.scheme())),
+        // A link to another host using the same protocol
+        Err(ParseError::RelativeUrlWithoutBase) if url.starts_with("//") => return None,
+        Err(ParseError::RelativeUrlWithoutBase) => local(url, page, root)?,
+        Err(_) => return None,
+    };
+    if fmt.asset.is_empty() {
+        return None;
+    }
+    let ext = file
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_lowercase);
+    if matches!(ext.as_deref(), Some("gmi" | "gemini")) {
+        return None;
+    }
+    let meta = fs::metadata(&file).ok().filter(fs::Metadata::is_file)?;
+    let kind = match ext.as_deref() {
+        Some("jpg" | "jpeg") => "JPEG".to_string(),
+        Some("htm") => "HTML".to_string(),
+        Some(e) => e.to_uppercase(),
+        None => strings::gettext("file"),
+    };
+    Some(
+        fmt.asset
+            .replace("{type}", &kind)
+            .replace("{size}", &size(meta.len())),
+    )
+}
+
+/// Finds the file in the generated capsule which a relative or absolute link
+/// path refers to. Returns `None` if the path leads outside of the capsule.
+fn local(url: &str, page: &Path, root: &Path) -> Option<PathBuf> {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    let path = percent_decode_str(path).decode_utf8_lossy();
+    let (base, path) = match path.strip_prefix('/') {
+        Some(p) => {
+            let prefix = crate::load_config()
+                .path
+                .as_deref()
+                .map_or("", |p| p.trim_matches('/'));
+            let p = if prefix.is_empty() {
+                p
+            } else {
+                p.strip_prefix(prefix)?.trim_start_matches('/')
+            };
+            (root.to_path_buf(), p.to_string())
+        }
+        None => (page.parent()?.to_path_buf(), path.to_string()),
+    };
+    let mut file = base;
+    for component in Path::new(&path).components() {
+        match component {
+            Component::Normal(c) => file.push(c),
+            Component::ParentDir => {
+                file.pop();
+            }
+            _ => {}
+        }
+    }
+    file.starts_with(root).then_some(file)
+}
+
+/// Formats a size in bytes using decimal units, with one decimal place
+fn size(bytes: u64) -> String {
+    const UNITS: [(u64, &str); 4] = [
+        (1_000_000_000_000, "TB"),
+        (1_000_000_000, "GB"),
+        (1_000_000, "MB"),
+        (1000, "KB"),
+    ];
+    match UNITS.into_iter().find(|(scale, _)| bytes >= *scale) {
+        Some((scale, unit)) => {
+            // Rounded to the nearest tenth of the unit
+            let tenths = (u128::from(bytes) * 10 + u128::from(scale) / 2) / u128::from(scale);
+            format!("{}.{} {unit}", tenths / 10, tenths % 10)
+        }
+        None => format!("{bytes} B"),
+    }
+}
@@ -0,0 +1,81 @@
+use {
+    super::Meta,
+    crate::strings,
+    std::path::{Path, PathBuf},
+};
+
+/// Returns true if `s` looks like a language code such as `it` or `pt-BR`
+fn is_code(s: &str) -> bool {
+    let (lang, region) = match s.split_once(['-', '_']) {
+        Some((l, r)) => (l, Some(r)),
+        None => (s, None),
+    };
+    lang.len() == 2
+        && lang.chars().all(|c| c.is_ascii_lowercase())
+        && region.is_none_or(|r| r.len() == 2 && r.chars().all(|c| c.is_ascii_alphabetic()))
+}
+
+/// Splits a language suffix, such as the `it` in `post.it.gmi`, from the file
+/// name. Returns the path without the suffix and the suffix if present.
+pub fn split(path: &Path) -> (PathBuf, Option<String>) {
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    if let Some((name, code)) = stem.rsplit_once('.') {
+        if is_code(code) {
+            let mut base = path.with_file_name(name);
+            if let Some(ext) = path.extension() {
+                base.set_extension(ext);
+            }
+            return (base, Some(code.to_string()));
+        }
+    }
+    (path.to_path_buf(), None)
+}
+
+/// Returns the language of a page. This is the `lang` field of the page's
+/// metadata if set, then the language suffix of the file name, then the
+/// default language from `Config.ron`.
+pub fn resolve(path: &Path, meta: Option<&Meta>) -> Option<String> {
+    meta.and_then(|m| m.lang.clone())
+        .or_else(|| split(path).1)
+        .or_else(|| crate::load_config().lang.clone())
+}
+
+/// Returns true if `lang` is the default language of the capsule
+pub fn is_default(lang: Option<&str>) -> bool {
+    lang.is_none() || lang == crate::load_config().lang.as_deref()
+}
+
+/// Inserts the language suffix into the file name, unless `lang` is the
+/// default language
+pub fn with_lang(path: &Path, lang: Option<&str>) -> PathBuf {
+    match lang {
+        Some(l) if !is_default(lang) => {
+            let stem = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or_default();
+            let mut file = format!("{stem}.{l}");
+            if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+                file.push('.');
+                file.push_str(ext);
+            }
+            path.with_file_name(file)
+        }
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Returns true if both are the same language, treating the default language
+/// and an unset language as equal
+pub fn matches(a: Option<&str>, b: Option<&str>) -> bool {
+    (is_default(a) && is_default(b)) || a == b
+}
+
+/// Returns the name used when linking to a version of a page in `lang`
+pub fn label(lang: Option<&str>) -> String {
+    lang.or(crate::load_config().lang.as_deref())
+        .map_or_else(|| strings::gettext("default language"), ToString::to_string)
+}
@@ -1,12 +1,33 @@
+/// Size, type and protocol annotations for link lines
+pub mod annotate;
 /// Get an appropriate editor
 mod editor;
 /// Working with the main and gemlog indexes
 pub mod index;
+/// Languages and translated variants of pages
+pub mod lang;
+/// Output paths of published pages
+pub mod permalink;
+/// Per directory defaults
+pub mod section;
+/// File names and urls generated from titles
+pub mod slug;
+/// Word counts and reading time
+pub mod stats;
+/// File names and urls for tag pages
+pub mod tag;
 /// Date and time functionality
 mod time;
+/// Tables of contents and splitting long pages into sections
+pub mod toc;
 
 use {
-    crate::{config::DisplayDate, ToDisk},
+    crate::{
+        config::DisplayDate,
+        strings::{self, TAGS_DIR},
+        template::{self, Template, Vars},
+        ToDisk,
+    },
     atom_syndication as atom,
     extract_frontmatter::{config::Splitter, Extractor},
     gettextrs::gettext,
@@ -17,15 +38,16 @@ use {
     serde::{Deserialize, Serialize},
     std::{
         borrow::Cow,
+        fmt::Write as _,
         fs::{self, File},
-        io::{BufWriter, Write},
+        io::{BufWriter, Write as _},
         path::{Path, PathBuf},
         string::ToString,
     },
     tinylog::Time as _,
-    url::Url,
+    walkdir::WalkDir,
 };
-pub use {editor::edit, time::Time};
+pub use {editor::edit, section::Section, time::Time};
 
 #[derive(Clone, Debug)]
 /// The content type, page or post
@@ -35,8 +57,8 @@ pub enum Kind {
         /// The path from the capsule root to this document
         Option<PathBuf>,
     ),
-    /// A gemlog post
-    Post,
+    /// An entry in the named collection, such as a gemlog post
+    Post(String),
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
@@ -51,6 +73,34 @@ pub struct Meta {
     pub published: Option<Time>,
     /// Categories for this page
     pub tags: Vec<String>,
+    /// The language this page is written in, if different from the default set
+    /// in `Config.ron`
+    pub lang: Option<String>,
+    /// The name of the template used to render this page, overriding the
+    /// default set in `Config.ron`
+    pub template: Option<String>,
+    /// Set to `Some(false)` to leave the ascii art banner off of this page
+    pub banner: Option<bool>,
+    /// Whether to insert a table of contents built from the page's headings
+    #[serde(default)]
+    pub toc: bool,
+    /// Whether to split the page into one sub-page per `##` section, linked
+    /// from a table of contents
+    #[serde(default)]
+    pub split: bool,
+    /// The name used for this page's file, if it was not generated from the
+    /// title
+    #[serde(default)]
+    pub slug: Option<String>,
+    /// Paths, relative to the capsule root, which this page was published at
+    /// before it was renamed or moved. Each of them is redirected to the page.
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// A permanent identifier for this page, such as a `tag:` URI, used as the
+    /// id of it's Atom entry. It is generated when the page is created and
+    /// kept when the page is renamed or moved.
+    #[serde(default)]
+    pub id: Option<String>,
 }
 
 pub type Categories = Vec<atom::Category>;
@@ -62,22 +112,22 @@ impl TryFrom<&Meta> for Categories {
         let cfg = crate::load_config();
         let mut categories = Vec::new();
         for tag in &meta.tags {
-            let mut url = Url::parse(&format!("gemini://{}", cfg.domain))?;
-            let mut path = match &cfg.path {
-                Some(p) => PathBuf::from(&p),
-                None => PathBuf::from("/"),
-            };
-            path.push(&PathBuf::from(gettext("tags")));
-            path.push(&PathBuf::from(&tag));
-            path.set_extension("gmi");
-            let path = path.to_string_lossy();
+            let term = tag::slug(tag);
+            if categories.iter().any(|c: &atom::Category| c.term == term) {
+                continue;
+            }
+            let mut url = cfg.url()?;
+            let path = format!(
+                "{}/{TAGS_DIR}/{}",
+                url.path().trim_end_matches('/'),
+                tag::file(tag)
+            );
             url.set_path(&path);
-            let cat = atom::Category {
-                term: tag.clone(),
+            categories.push(atom::Category {
+                term,
                 scheme: Some(url.to_string()),
-                label: Some(tag.clone()),
-            };
-            categories.push(cat);
+                label: Some(tag::name(tag)),
+            });
         }
         Ok(categories)
     }
@@ -90,14 +140,55 @@ impl Meta {
         self.published = Some(Time::now());
     }
 
-    /// Given the title and `Kind` of this item, returns the path to the source file
+    /// Generates a `tag:` URI for the page created today at `path`, using the
+    /// domain which serves the capsule
+    pub fn new_id(domain: &str, path: &Path) -> String {
+        let path = path.strip_prefix("content").unwrap_or(path);
+        let name = path
+            .with_extension("")
+            .components()
+            .map(|c| slug::slugify(&c.as_os_str().to_string_lossy()))
+            .collect::<Vec<_>>()
+            .join("/");
+        // The authority of a tag URI is a bare domain name
+        let domain = domain.split(':').next().unwrap_or_default();
+        format!("tag:{domain},{}:{name}", Time::now().date_string())
+    }
+
+    /// Given the title or slug and `Kind` of this item, returns the path to the
+    /// source file. Posts which already exist are found wherever they are in
+    /// their collection, and new posts are placed according to it's layout.
     pub fn get_path(title: &str, kind: Kind) -> PathBuf {
-        let mut tpath = sanitize_path(title);
-        tpath.push_str(".gmi");
+        let slug = slug::slugify(title);
+        let tpath = format!("{slug}.gmi");
+        // Drafts created before file names were slugified
+        let legacy = format!("{}.gmi", legacy_name(title));
         match kind {
             Kind::Page(Some(path)) => path,
-            Kind::Page(None) => ["content", &tpath].iter().collect(),
-            Kind::Post => ["content", "gemlog", &tpath].iter().collect(),
+            Kind::Page(None) => {
+                let path: PathBuf = ["content", &tpath].iter().collect();
+                let old: PathBuf = ["content", &legacy].iter().collect();
+                if !path.exists() && old.exists() {
+                    old
+                } else {
+                    path
+                }
+            }
+            Kind::Post(collection) => {
+                let dir = Path::new("content").join(&collection);
+                let find = |name: &str| {
+                    WalkDir::new(&dir)
+                        .into_iter()
+                        .flatten()
+                        .find(|e| e.file_type().is_file() && e.file_name() == name)
+                        .map(walkdir::DirEntry::into_path)
+                };
+                find(&tpath).or_else(|| find(&legacy)).unwrap_or_else(|| {
+                    let layout = crate::load_config().layout(&collection);
+                    let path = Time::now().expand(&layout).replace("{slug}", &slug);
+                    dir.join(format!("{path}.gmi"))
+                })
+            }
         }
     }
 }
@@ -141,31 +232,37 @@ impl Page {
         }
     }
 
-    /// Create a new `Page`
+    /// Create a new `Page`. The file name is generated from `slug` if given,
+    /// or else from the title.
+    /// # Errors
+    /// Returns an error if the page would overwrite or be confused with an
+    /// existing file, or if it cannot be written
     pub fn create(
         kind: Kind,
         title: &str,
+        slug: Option<&str>,
         summary: Option<&str>,
         tags: Vec<String>,
     ) -> Result<PathBuf, crate::Error> {
-        let mut tpath = sanitize_path(title);
-        tpath.push_str(".gmi");
-        let file = match kind {
-            Kind::Page(Some(path)) => path,
-            Kind::Page(None) => {
-                let mut path = PathBuf::from("content");
-                path.push(&tpath);
-                path
-            }
-            Kind::Post => {
-                let mut path = PathBuf::from("content");
-                path.push("gemlog");
-                path.push(&tpath);
-                path
+        let file = Meta::get_path(slug.unwrap_or(title), kind);
+        let parent = file.parent().unwrap_or(Path::new(""));
+        if file.exists() {
+            return Err(format!("{}: {}", gettext("File already exists"), file.display()).into());
+        }
+        if parent.exists() {
+            let name = slug::slugify(&file.file_stem().unwrap_or_default().to_string_lossy());
+            for entry in fs::read_dir(parent)?.flatten() {
+                if slug::collides(&entry.path(), &name) {
+                    return Err(format!(
+                        "{} `{name}` {}: {}",
+                        gettext("The file name"),
+                        gettext("collides with an existing page"),
+                        entry.path().display()
+                    )
+                    .into());
+                }
             }
-        };
-        let parent = file.parent().unwrap();
-        if !parent.exists() {
+        } else {
             fs::create_dir_all(parent)?;
         }
         let meta = Meta {
@@ -173,6 +270,14 @@ impl Page {
             summary: summary.map(ToString::to_string),
             published: None,
             tags,
+            lang: None,
+            template: None,
+            banner: None,
+            toc: false,
+            split: false,
+            slug: slug.map(slug::slugify),
+            aliases: Vec::new(),
+            id: Some(Meta::new_id(&crate::load_config().domain, &file)),
         };
         let page = Self {
             meta,
@@ -183,12 +288,23 @@ impl Page {
     }
 
     /// Publish a page given it's `Kind` and title
+    /// # Errors
+    /// Returns an error if there is no such page, or if it cannot be read or
+    /// written
     pub fn publish(kind: Kind, title: &str) -> Result<(), crate::Error> {
         let path = Meta::get_path(title, kind);
-        if let Some(mut page) = Self::from_path(&path) {
-            page.meta.publish();
-            page.to_disk(&path)?;
+        if !path.exists() {
+            return Err(format!("{}: {}", gettext("No such draft"), path.display()).into());
+        }
+        let Some(mut page) = Self::from_path(&path) else {
+            return Err(format!("{}: {}", gettext("Invalid page"), path.display()).into());
+        };
+        page.meta.publish();
+        // Pages created before ids were generated get one when published
+        if page.meta.id.is_none() {
+            page.meta.id = Some(Meta::new_id(&crate::load_config().domain, &path));
         }
+        page.to_disk(&path)?;
         Ok(())
     }
 
@@ -200,79 +316,200 @@ impl Page {
     }
 
     /// Render a page and save it to disk
-    pub fn write(
-        &self,
-        path: &Path,
-        depth: usize,
-        banner: &Option<String>,
-    ) -> Result<(), crate::Error> {
-        let cfg = crate::load_config();
+    pub fn write(&self, path: &Path, ctx: &Context) -> Result<(), crate::Error> {
         if let Some(p) = path.parent() {
             if !p.exists() {
                 fs::create_dir_all(p)?;
             }
         }
-        let fd = File::create(path)?;
-        let mut writer = BufWriter::new(fd);
-        if let Some(s) = banner {
-            writeln!(&mut writer, "```\n{s}\n```")?;
+        let target = Target {
+            path,
+            depth: ctx.depth,
+            is_post: ctx.all_posts.is_some(),
+            all_posts: ctx.all_posts.clone().unwrap_or_default(),
+            ctx,
+        };
+        if self.meta.split {
+            return self.write_split(&target);
         }
-        writeln!(&mut writer, "# {}", self.meta.title)?;
-        match &cfg.display_date {
-            DisplayDate::Always | DisplayDate::GemlogOnly
-                if path.parent().unwrap().to_str().unwrap().ends_with("gemlog") =>
-            {
+        let content = if self.meta.toc || toc::contains(&self.content) {
+            toc::insert(&self.content)?
+        } else {
+            self.content.clone()
+        };
+        let page = self.render(&target, content, String::new())?;
+        fs::write(path, page)?;
+        Ok(())
+    }
+
+    /// Writes the introduction and a table of contents to `path`, and each `##`
+    /// section to it's own page in a subdirectory of the same name
+    fn write_split(&self, target: &Target) -> Result<(), crate::Error> {
+        let (intro, sections) = toc::split(&self.content)?;
+        let Some(stem) = target.path.file_stem().and_then(|s| s.to_str()) else {
+            return Err(gettext("Invalid file name").into());
+        };
+        let mut dir = target.path.to_path_buf();
+        dir.set_extension("");
+        if !dir.exists() {
+            fs::create_dir_all(&dir)?;
+        }
+        let mut contents = format!("{intro}\n\n### {}\n", strings::gettext("Contents"));
+        for (n, (heading, _)) in sections.iter().enumerate() {
+            writeln!(contents, "=> {stem}/{}.gmi {heading}", n + 1)?;
+        }
+        let contents = contents.trim_end().to_string();
+        fs::write(target.path, self.render(target, contents, String::new())?)?;
+        for (n, (_, body)) in sections.iter().enumerate() {
+            let mut nav = String::new();
+            if n > 0 {
                 writeln!(
-                    &mut writer,
-                    "### {}\n{}\n",
-                    self.meta.published.as_ref().unwrap().date_string(),
-                    self.content
+                    nav,
+                    "=> {n}.gmi {}: {}",
+                    strings::gettext("Previous"),
+                    sections[n - 1].0
                 )?;
             }
-            _ => writeln!(&mut writer, "{}\n", self.content)?,
+            if let Some((heading, _)) = sections.get(n + 1) {
+                writeln!(
+                    nav,
+                    "=> {}.gmi {}: {heading}",
+                    n + 2,
+                    strings::gettext("Next")
+                )?;
+            }
+            writeln!(nav, "=> ../{stem}.gmi {}", strings::gettext("Contents"))?;
+            let mut path = dir.clone();
+            path.push(format!("{}.gmi", n + 1));
+            let sub = Target {
+                path: &path,
+                depth: target.depth + 1,
+                is_post: target.is_post,
+                all_posts: match target.all_posts.as_str() {
+                    "." => "..".to_string(),
+                    p => format!("../{p}"),
+                },
+                ctx: target.ctx,
+            };
+            fs::write(&path, self.render(&sub, body.trim_end().to_string(), nav)?)?;
         }
-        if path.file_name().unwrap().to_str().unwrap() == "tinylog.gmi" {
-            return Ok(());
+        Ok(())
+    }
+
+    /// Renders the page with the given content, adding the tags, navigation
+    /// links and footer
+    fn render(
+        &self,
+        target: &Target,
+        content: String,
+        mut nav: String,
+    ) -> Result<String, crate::Error> {
+        let cfg = crate::load_config();
+        let kind = if target.is_post {
+            template::Kind::Post
+        } else {
+            template::Kind::Page
+        };
+        let mut vars = Vars {
+            banner: crate::banner::block(target.ctx.banner),
+            title: self.meta.title.clone(),
+            content,
+            ..Default::default()
+        };
+        match target.ctx.section.display_date() {
+            DisplayDate::Always => {
+                vars.date = format!(
+                    "### {}\n",
+                    self.meta.published.as_ref().unwrap().date_string()
+                );
+            }
+            DisplayDate::GemlogOnly if target.is_post => {
+                vars.date = format!(
+                    "### {}\n",
+                    self.meta.published.as_ref().unwrap().date_string()
+                );
+            }
+            _ => {}
+        }
+        if let Some(line) = stats::line(target.ctx.words) {
+            writeln!(vars.date, "{line}")?;
+        }
+        if target.path.file_name().unwrap().to_str().unwrap() == "tinylog.gmi" {
+            return Ok(format!(
+                "{}# {}\n{}{}\n\n",
+                vars.banner, vars.title, vars.date, vars.content
+            ));
         }
         if !self.meta.tags.is_empty() {
-            writeln!(&mut writer, "### {}", gettext("Tags for this page"))?;
+            writeln!(vars.tags, "### {}", strings::gettext("Tags for this page"))?;
             let u = cfg.url()?;
+            let mut seen = vec![];
             for tag in &self.meta.tags {
-                match depth {
-                    1 => writeln!(&mut writer, "=> {}/{tag}.gmi {tag}", gettext("tags"))?,
-                    2 => writeln!(&mut writer, "=> ../{}/{tag}.gmi {tag}", gettext("tags"))?,
-                    3 => writeln!(&mut writer, "=> ../../{}/{tag}.gmi {tag}", gettext("tags"))?,
-                    _ => writeln!(&mut writer, "=> {u}/{}/{tag}.gmi {tag}", gettext("tags"))?,
+                let file = tag::file(tag);
+                if seen.contains(&file) {
+                    continue;
+                }
+                let tag = tag::name(tag);
+                match target.depth {
+                    1 => writeln!(vars.tags, "=> {TAGS_DIR}/{file} {tag}")?,
+                    2 => writeln!(vars.tags, "=> ../{TAGS_DIR}/{file} {tag}")?,
+                    3 => writeln!(vars.tags, "=> ../../{TAGS_DIR}/{file} {tag}")?,
+                    _ => writeln!(vars.tags, "=> {u}/{TAGS_DIR}/{file} {tag}")?,
                 }
+                seen.push(file);
             }
-            writeln!(&mut writer)?;
+            writeln!(vars.tags)?;
         }
         writeln!(
-            &mut writer,
+            nav,
             "=> {} {}",
-            match depth {
+            match target.depth {
                 1 => Cow::from("."),
                 2 => Cow::from(".."),
                 _ => Cow::from(cfg.url()?.to_string()),
             },
-            gettext("Home"),
+            strings::gettext("Home"),
         )?;
-        if let Some(p) = path.parent() {
-            if let Some(n) = p.file_name() {
-                if let Some(s) = n.to_str() {
-                    if s == "gemlog" {
-                        writeln!(&mut writer, "=> . {}", gettext("All posts"))?;
-                    }
-                }
-            }
+        if target.is_post {
+            writeln!(
+                nav,
+                "=> {} {}",
+                target.all_posts,
+                strings::gettext("All posts")
+            )?;
         }
+        vars.nav = nav;
+        vars.translations.clone_from(&target.ctx.translations);
         let year = self.meta.published.as_ref().unwrap().year();
-        crate::write_footer(&mut writer, year.try_into().expect("Year is out of range"))?;
-        Ok(())
+        vars.footer = crate::footer(
+            year.try_into().expect("Year is out of range"),
+            target.ctx.section.footer_links(),
+        )?;
+        let tmpl = Template::get(self.meta.template.as_deref(), kind)?;
+        Ok(tmpl.render(&vars))
     }
 }
 
-fn sanitize_path(title: &str) -> String {
+/// Where a page sits in the capsule, used when rendering it
+pub struct Context<'a> {
+    /// How deep in the generated capsule this page is
+    pub depth: usize,
+    /// The ascii art banner
+    pub banner: Option<&'a str>,
+    /// Defaults for the directory this page is in
+    pub section: &'a Section,
+    /// Links to the translations of this page
+    pub translations: String,
+    /// The relative url of the index of the collection this page is an entry
+    /// in, if any
+    pub all_posts: Option<String>,
+    /// The number of words in the page
+    pub words: usize,
+}
+
+/// Returns the file name, without extension, which was generated from a title
+/// before titles were slugified
+fn legacy_name(title: &str) -> String {
     title
         .trim()
         .to_lowercase()
@@ -281,3 +518,16 @@ fn sanitize_path(title: &str) -> String {
         .replace('.', "_")
         .replace('\'', "")
 }
+
+/// Where a rendered page is written and how it relates to the rest of the capsule
+struct Target<'a> {
+    /// The output path
+    path: &'a Path,
+    /// How deep in the capsule the output file is
+    depth: usize,
+    /// Whether this page is an entry in a collection
+    is_post: bool,
+    /// The relative url of the collection's index
+    all_posts: String,
+    ctx: &'a Context<'a>,
+}
@@ -0,0 +1,68 @@
+use {
+    super::{lang, Meta},
+    std::{
+        borrow::Cow,
+        path::{Component, Path, PathBuf},
+    },
+};
+
+/// Returns the path, relative to the capsule root, which the page found at
+/// `path` within the content directory is written to. Published pages are
+/// moved to the permalink configured for pages or for posts, depending on
+/// whether they belong to a collection. Index pages, and every page when no
+/// pattern is configured, keep the path they have in the content directory.
+pub fn path(path: &Path, meta: &Meta, collection: Option<&str>) -> PathBuf {
+    let cfg = &crate::load_config().permalinks;
+    let pattern = match collection {
+        Some(_) => cfg.post.as_deref(),
+        None => cfg.page.as_deref(),
+    };
+    let (base, lang) = lang::split(path);
+    let stem = base
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    let (Some(pattern), Some(time)) = (pattern, meta.published) else {
+        return path.to_path_buf();
+    };
+    if stem == "index" {
+        return path.to_path_buf();
+    }
+    let section = match collection {
+        Some(c) => Cow::from(c),
+        None => base.parent().unwrap_or(Path::new("")).to_string_lossy(),
+    };
+    let expanded = time
+        .expand(pattern)
+        .replace("{slug}", meta.slug.as_deref().unwrap_or(stem))
+        .replace("{section}", &section);
+    // Empty placeholders leave doubled slashes behind
+    let mut permalink = normalize(&expanded);
+    let name = match permalink.file_name() {
+        Some(n) => n.to_string_lossy().to_string(),
+        None => stem.to_string(),
+    };
+    let name = match lang {
+        Some(l) => format!("{name}.{l}.gmi"),
+        None => format!("{name}.gmi"),
+    };
+    permalink.set_file_name(name);
+    permalink
+}
+
+/// Returns one of a page's aliases as a path relative to the capsule root
+pub fn alias(alias: &str) -> PathBuf {
+    normalize(alias)
+}
+
+/// Removes empty, `.` and `..` components and any leading slash from a path,
+/// so that it cannot lead outside of the capsule
+fn normalize(path: &str) -> PathBuf {
+    Path::new(path)
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(c) => Some(c),
+            _ => None,
+        })
+        .collect()
+}
@@ -0,0 +1,115 @@
+use {
+    super::Meta,
+    crate::{
+        config::{DisplayDate, SortBy},
+        link::Link,
+    },
+    gettextrs::gettext,
+    serde::{Deserialize, Serialize},
+    std::{fs, path::Path},
+};
+
+/// The name of the file holding the defaults for a directory
+pub const FILE: &str = "_section.ron";
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+/// Defaults for every page in a directory and it's subdirectories, read from
+/// `_section.ron`. Values set in a page's own metadata take precedence.
+pub struct Section {
+    /// Tags for pages which do not set any of their own
+    pub tags: Option<Vec<String>>,
+    /// Whether to display the ascii art banner
+    pub banner: Option<bool>,
+    /// Which pages to display the publication date for
+    pub display_date: Option<DisplayDate>,
+    /// The name of the template used to render pages
+    pub template: Option<String>,
+    /// Links to display at the bottom of each page, in place of those set in
+    /// `Config.ron`
+    pub footer_links: Option<Vec<Link>>,
+    /// Whether to generate an index page if the directory does not have one
+    pub index: Option<bool>,
+    /// The order in which pages are listed in the generated index
+    pub sort: Option<SortBy>,
+}
+
+impl Section {
+    /// Reads the section file from `dir`, if there is one
+    /// # Errors
+    /// Returns an error if the file exists but cannot be read or decoded
+    pub fn load(dir: &Path) -> Result<Option<Self>, crate::Error> {
+        let file = dir.join(FILE);
+        if !file.exists() {
+            return Ok(None);
+        }
+        match ron::de::from_str(&fs::read_to_string(&file)?) {
+            Ok(s) => Ok(Some(s)),
+            Err(e) => {
+                eprintln!("{} {}: {e}", gettext("Error decoding"), file.display());
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Returns a new `Section` where values set in `child` override those set
+    /// in `self`
+    #[must_use]
+    pub fn merge(&self, child: &Self) -> Self {
+        Self {
+            tags: child.tags.clone().or_else(|| self.tags.clone()),
+            banner: child.banner.or(self.banner),
+            display_date: child
+                .display_date
+                .clone()
+                .or_else(|| self.display_date.clone()),
+            template: child.template.clone().or_else(|| self.template.clone()),
+            footer_links: child
+                .footer_links
+                .clone()
+                .or_else(|| self.footer_links.clone()),
+            index: child.index.or(self.index),
+            sort: child.sort.or(self.sort),
+        }
+    }
+
+    /// Fills in any values which are not set in the page's metadata
+    pub fn apply(&self, meta: &mut Meta) {
+        if meta.tags.is_empty() {
+            if let Some(tags) = &self.tags {
+                meta.tags.clone_from(tags);
+            }
+        }
+        if meta.banner.is_none() {
+            meta.banner = self.banner;
+        }
+        if meta.template.is_none() {
+            meta.template.clone_from(&self.template);
+        }
+    }
+
+    /// Returns the date display policy for this section
+    pub fn display_date(&self) -> &DisplayDate {
+        self.display_date
+            .as_ref()
+            .unwrap_or(&crate::load_config().display_date)
+    }
+
+    /// Returns whether an index page should be generated for this section
+    pub fn index(&self) -> bool {
+        self.index
+            .unwrap_or(crate::load_config().auto_index.enabled)
+    }
+
+    /// Returns the order in which pages are listed in the generated index
+    pub fn sort(&self) -> SortBy {
+        self.sort.unwrap_or(crate::load_config().auto_index.sort)
+    }
+
+    /// Returns the footer links for this section
+    pub fn footer_links(&self) -> &[Link] {
+        self.footer_links
+            .as_deref()
+            .unwrap_or(&crate::load_config().footer_links)
+    }
+}
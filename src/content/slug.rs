@@ -0,0 +1,117 @@
+use {deunicode::deunicode, std::path::Path};
+
+/// The longest slug which will be generated, in bytes
+pub const MAX_LEN: usize = 64;
+
+/// The slug used when nothing usable is left of the title
+const FALLBACK: &str = "untitled";
+
+/// Converts a title into a name which is safe to use as a file name and in a
+/// url. Letters from other scripts are transliterated into ASCII, apostrophes
+/// are dropped and every other run of characters outside of `a-z` and `0-9` is
+/// replaced with a single `_`, or kept as a `-` if it consists only of hyphens.
+/// The result is lowercase, no longer than `MAX_LEN` and never empty. Passing
+/// a slug through this function again leaves it unchanged.
+pub fn slugify(title: &str) -> String {
+    let ascii = deunicode(title).to_lowercase().replace('\'', "");
+    let mut slug = String::with_capacity(ascii.len());
+    let mut sep = String::new();
+    for c in ascii.chars() {
+        if c.is_ascii_alphanumeric() {
+            if !slug.is_empty() && !sep.is_empty() {
+                slug.push(if sep.chars().all(|s| s == '-') {
+                    '-'
+                } else {
+                    '_'
+                });
+            }
+            sep.clear();
+            slug.push(c);
+        } else {
+            sep.push(c);
+        }
+    }
+    if slug.len() > MAX_LEN {
+        // Cut at the last separator if that doesn't lose too much of the title
+        let cut = slug[..=MAX_LEN]
+            .rfind(['_', '-'])
+            .filter(|n| *n > MAX_LEN / 2)
+            .unwrap_or(MAX_LEN);
+        slug.truncate(cut);
+        slug = slug.trim_end_matches(['_', '-']).to_string();
+    }
+    if slug.is_empty() {
+        FALLBACK.to_string()
+    } else {
+        slug
+    }
+}
+
+/// Returns true if `path` is a page whose file name is the same as `slug` once
+/// it has been passed through [`slugify`], such as `My_Page.gmi` for `my_page`
+pub fn collides(path: &Path, slug: &str) -> bool {
+    path.extension().is_some_and(|e| e == "gmi")
+        && path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .is_some_and(|stem| slugify(stem) == slug)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TITLES: &[&str] = &[
+        "Hello, World!",
+        "Привет, мир",
+        "日本語のテキスト",
+        "Ελληνικά και Ñandú",
+        "a/b?c#d",
+        "/?#",
+        "Don't stop - it's well-known",
+        "",
+        "one two three four five six seven eight nine ten eleven twelve thirteen",
+        "supercalifragilisticexpialidocioussupercalifragilisticexpialidocious_again",
+    ];
+
+    #[test]
+    fn non_latin() {
+        assert_eq!(slugify("Привет, мир"), "privet_mir");
+        assert_eq!(slugify("Ελληνικά και Ñandú"), "ellenika_kai_nandu");
+        assert!(slugify("日本語のテキスト")
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_'));
+    }
+
+    #[test]
+    fn url_characters() {
+        assert_eq!(slugify("a/b?c#d"), "a_b_c_d");
+        assert_eq!(slugify("/?#"), FALLBACK);
+        assert_eq!(slugify("../etc/passwd"), "etc_passwd");
+        assert_eq!(
+            slugify("Don't stop - it's well-known"),
+            "dont_stop_its_well-known"
+        );
+    }
+
+    #[test]
+    fn length_cap() {
+        let words = slugify(TITLES[8]);
+        assert!(words.len() <= MAX_LEN);
+        assert!(TITLES[8].replace(' ', "_").starts_with(&words));
+        assert!(!words.ends_with('_'));
+        let word = slugify(TITLES[9]);
+        assert_eq!(word.len(), MAX_LEN);
+        for title in TITLES {
+            assert!(slugify(title).len() <= MAX_LEN, "{title}");
+        }
+    }
+
+    #[test]
+    fn idempotent() {
+        for title in TITLES {
+            let slug = slugify(title);
+            assert_eq!(slugify(&slug), slug, "{title}");
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use crate::strings;
+
+/// Counts the words in a gemtext document. Preformatted blocks and the urls of
+/// link lines are not counted.
+pub fn word_count(content: &str) -> usize {
+    let mut pre = false;
+    let mut words = 0;
+    for line in content.lines() {
+        if line.starts_with("```") {
+            pre = !pre;
+            continue;
+        }
+        if pre || line.starts_with("{%") {
+            continue;
+        }
+        let text = match line.strip_prefix("=>") {
+            Some(link) => link
+                .split_whitespace()
+                .skip(1)
+                .collect::<Vec<_>>()
+                .join(" "),
+            None => line.trim_start_matches(['#', '*', '>']).to_string(),
+        };
+        words += text.split_whitespace().count();
+    }
+    words
+}
+
+/// Returns the estimated reading time in minutes, rounded up
+pub fn reading_time(words: usize, words_per_minute: usize) -> usize {
+    words.div_ceil(words_per_minute.max(1)).max(1)
+}
+
+/// Returns a line giving the word count and reading time, if enabled in the
+/// capsule configuration and the page has any words
+pub fn line(words: usize) -> Option<String> {
+    let wpm = crate::load_config().reading_time?;
+    if words == 0 {
+        return None;
+    }
+    Some(format!(
+        "{words} {}, {} {} {}",
+        strings::gettext("words"),
+        strings::gettext("about"),
+        reading_time(words, wpm),
+        strings::gettext("min read"),
+    ))
+}
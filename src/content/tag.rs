@@ -0,0 +1,73 @@
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+
+/// The characters which must be percent encoded within a single url path
+/// segment
+const SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// The character separating the levels of a hierarchical tag
+pub const SEPARATOR: char = '/';
+
+/// Returns the tag as it is displayed, with runs of whitespace collapsed and
+/// empty levels removed
+pub fn name(tag: &str) -> String {
+    tag.split(SEPARATOR)
+        .map(|level| level.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|level| !level.is_empty())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Returns the path, without extension, of a tag's page within the tags
+/// directory. Tags which differ only in case or in the whitespace they use
+/// share the same slug, and are treated as a single tag. Letters from other
+/// scripts are kept, and are percent encoded wherever they appear in a url.
+/// Each level of a hierarchical tag such as `os/linux` is a directory.
+pub fn slug(tag: &str) -> String {
+    name(tag)
+        .split(SEPARATOR)
+        .map(|level| {
+            let level = level.replace(' ', "_").to_lowercase().replace('\\', "_");
+            // Keep the page from being hidden or escaping the tags directory
+            match level.strip_prefix('.') {
+                Some(s) => format!("_{s}"),
+                None => level,
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Returns the path of a tag's page within the tags directory, percent encoded
+/// for use in a url
+pub fn file(tag: &str) -> String {
+    let path = slug(tag)
+        .split(SEPARATOR)
+        .map(|level| utf8_percent_encode(level, SEGMENT).to_string())
+        .collect::<Vec<_>>()
+        .join("/");
+    format!("{path}.gmi")
+}
+
+/// Returns the slugs of every tag above this one in the hierarchy, starting
+/// from the top
+pub fn parents(slug: &str) -> impl Iterator<Item = &str> {
+    slug.match_indices(SEPARATOR).map(|(n, _)| &slug[..n])
+}
+
+/// Returns true if the tag with slug `slug` is `parent` or is below it in the
+/// hierarchy
+pub fn within(slug: &str, parent: &str) -> bool {
+    slug.strip_prefix(parent)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(SEPARATOR))
+}
@@ -96,6 +96,15 @@ impl Time {
         Ok(self.to_date_time()?.timestamp())
     }
 
+    /// Replaces `{year}`, `{month}` and `{day}` in `pattern` with this date.
+    /// Months and days are padded to two digits.
+    pub fn expand(&self, pattern: &str) -> String {
+        pattern
+            .replace("{year}", &self.year.to_string())
+            .replace("{month}", &format!("{:02}", self.month))
+            .replace("{day}", &format!("{:02}", self.day))
+    }
+
     /// Returns a string representing just the date protion (American format)
     pub fn date_string(&self) -> String {
         format!("{}-{:02}-{:02}", self.year, self.month, self.day)
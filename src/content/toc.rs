@@ -0,0 +1,143 @@
+use {
+    crate::{
+        gemtext::{Document, FENCE},
+        strings,
+    },
+    std::fmt::Write,
+};
+
+/// The marker which is replaced with the table of contents
+pub const MARKER: &str = "{% toc %}";
+
+/// Returns the `##` and `###` headings in the content, skipping any which appear
+/// inside of preformatted blocks. Lines starting with more than three `#` are
+/// left out, as they are not headings of any level.
+fn headings(content: &str) -> Vec<(u8, String)> {
+    Document::parse(content)
+        .headings()
+        .filter(|(level, text)| *level > 1 && !text.starts_with('#'))
+        .map(|(level, text)| (level, text.to_string()))
+        .collect()
+}
+
+/// Builds a table of contents from the headings in the content
+pub fn build(content: &str) -> Result<String, crate::Error> {
+    let mut toc = format!("### {}\n", strings::gettext("Contents"));
+    for (level, heading) in headings(content) {
+        match level {
+            2 => writeln!(toc, "* {heading}")?,
+            _ => writeln!(toc, "*   {heading}")?,
+        }
+    }
+    Ok(toc)
+}
+
+/// Replaces the first marker outside of preformatted blocks with `toc`, and
+/// removes any others. Returns `None` if there is no such marker.
+fn replace(content: &str, toc: &str) -> Option<String> {
+    let mut pre = false;
+    let mut found = false;
+    let mut out = String::with_capacity(content.len());
+    for line in content.split_inclusive('\n') {
+        if line.starts_with(FENCE) {
+            pre = !pre;
+        }
+        if pre || !line.contains(MARKER) {
+            out.push_str(line);
+            continue;
+        }
+        for (n, part) in line.split(MARKER).enumerate() {
+            if n > 0 && !found {
+                out.push_str(toc);
+                found = true;
+            }
+            out.push_str(part);
+        }
+    }
+    found.then_some(out)
+}
+
+/// Returns true if the content has a marker outside of preformatted blocks
+pub fn contains(content: &str) -> bool {
+    replace(content, "").is_some()
+}
+
+/// Removes every marker from the content
+fn strip(content: &str) -> String {
+    replace(content, "").unwrap_or_else(|| content.to_string())
+}
+
+/// Replaces the marker with the table of contents. Content without a marker is
+/// returned unchanged.
+pub fn fill(content: &str) -> Result<String, crate::Error> {
+    if !contains(content) {
+        return Ok(content.to_string());
+    }
+    let toc = build(content)?;
+    Ok(replace(content, &toc).unwrap_or_else(|| content.to_string()))
+}
+
+/// Inserts the table of contents in place of the marker, or at the beginning
+/// of the content if the marker is absent
+pub fn insert(content: &str) -> Result<String, crate::Error> {
+    if contains(content) {
+        fill(content)
+    } else {
+        Ok(format!("{}\n{content}", build(content)?))
+    }
+}
+
+/// Splits the content into the text appearing before the first `##` heading
+/// and a list of sections, each beginning with it's heading
+pub fn split(content: &str) -> Result<(String, Vec<(String, String)>), crate::Error> {
+    let mut pre = false;
+    let mut intro = String::new();
+    let mut sections: Vec<(String, String)> = vec![];
+    for line in content.lines() {
+        if line.starts_with(FENCE) {
+            pre = !pre;
+        }
+        let heading = if pre {
+            None
+        } else {
+            line.strip_prefix("##").filter(|h| !h.starts_with('#'))
+        };
+        if let Some(h) = heading {
+            sections.push((h.trim().to_string(), String::new()));
+        }
+        match sections.last_mut() {
+            Some((_, body)) => writeln!(body, "{line}")?,
+            None => writeln!(intro, "{line}")?,
+        }
+    }
+    // The contents page already lists every section, so markers are dropped
+    for (_, body) in &mut sections {
+        *body = strip(body);
+    }
+    Ok((strip(&intro).trim().to_string(), sections))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preformatted_marker_kept() {
+        let page = replace("{% toc %}\n## One\n```\n{% toc %}\n```\n", "TOC\n");
+        assert_eq!(page.unwrap(), "TOC\n\n## One\n```\n{% toc %}\n```\n");
+        assert_eq!(replace("```\n{% toc %}\n", "TOC\n"), None);
+    }
+
+    #[test]
+    fn extra_markers_removed() {
+        let page = replace("{% toc %}\n## One\n{% toc %}\n", "TOC\n");
+        assert_eq!(page.unwrap(), "TOC\n\n## One\n\n");
+    }
+
+    #[test]
+    fn split_drops_markers() {
+        let (intro, sections) = split("a {% toc %}\n## One\n{% toc %}\nb\n").unwrap();
+        assert_eq!(intro, "a");
+        assert_eq!(sections, [("One".to_string(), "## One\n\nb\n".to_string())]);
+    }
+}
@@ -24,6 +24,14 @@ pub enum Error {
     TimeError(chrono::ParseError),
     /// An error parsing a url
     UrlError(url::ParseError),
+    /// An error setting up or during a TLS connection
+    TlsError(rustls::Error),
+    /// An error generating a certificate
+    CertificateError(rcgen::Error),
+    /// An error watching files for changes
+    WatchError(notify::Error),
+    /// An error decoding or encoding an image
+    ImageError(image::ImageError),
     /// Another, unexpected, error
     OtherError(String),
 }
@@ -82,6 +90,30 @@ impl From<url::ParseError> for Error {
     }
 }
 
+impl From<rustls::Error> for Error {
+    fn from(err: rustls::Error) -> Self {
+        Self::TlsError(err)
+    }
+}
+
+impl From<rcgen::Error> for Error {
+    fn from(err: rcgen::Error) -> Self {
+        Self::CertificateError(err)
+    }
+}
+
+impl From<notify::Error> for Error {
+    fn from(err: notify::Error) -> Self {
+        Self::WatchError(err)
+    }
+}
+
+impl From<image::ImageError> for Error {
+    fn from(err: image::ImageError) -> Self {
+        Self::ImageError(err)
+    }
+}
+
 impl From<String> for Error {
     fn from(s: String) -> Self {
         Self::OtherError(s)
@@ -106,6 +138,10 @@ impl fmt::Display for Error {
             Self::RonError(e) => write!(f, "Ron error: {e}"),
             Self::TimeError(e) => write!(f, "{}: {e}", gettext("Time error")),
             Self::UrlError(e) => write!(f, "{}: {e}", gettext("Url error")),
+            Self::TlsError(e) => write!(f, "{}: {e}", gettext("TLS error")),
+            Self::CertificateError(e) => write!(f, "{}: {e}", gettext("Certificate error")),
+            Self::WatchError(e) => write!(f, "{}: {e}", gettext("Watch error")),
+            Self::ImageError(e) => write!(f, "{}: {e}", gettext("Image error")),
             Self::EditorError(e) | Self::OtherError(e) => {
                 write!(f, "{}: {e}", gettext("Editor error"))
             }
@@ -121,6 +157,10 @@ impl error::Error for Error {
             Self::RonError(e) => Some(e),
             Self::TimeError(e) => Some(e),
             Self::UrlError(e) => Some(e),
+            Self::TlsError(e) => Some(e),
+            Self::CertificateError(e) => Some(e),
+            Self::WatchError(e) => Some(e),
+            Self::ImageError(e) => Some(e),
             _ => None,
         }
     }
@@ -0,0 +1,372 @@
+//! Gemtext is line oriented, and the type of each line is decided by the first
+//! few characters it begins with. A [`Document`] is parsed into a list of typed
+//! [`Line`]s and can be written back out as gemtext using it's `Display`
+//! implementation.
+//!
+//! ```
+//! use zond::gemtext::{Document, Line};
+//!
+//! let doc = Document::parse("# Hello\n=> gemini://example.org/ Example\n");
+//! assert_eq!(
+//!     doc.lines,
+//!     [
+//!         Line::Heading { level: 1, text: "Hello".to_string() },
+//!         Line::Link {
+//!             url: "gemini://example.org/".to_string(),
+//!             display: Some("Example".to_string()),
+//!         },
+//!     ]
+//! );
+//! ```
+//!
+//! ## Round trips
+//! Parsing the serialized form of a parsed document always gives back the same
+//! document.
+//! ```
+//! use zond::gemtext::Document;
+//!
+//! let text = "#Title\n=>  foo.gmi \t Foo\n>quoted\n```\nunclosed";
+//! let doc = Document::parse(text);
+//! assert_eq!(Document::parse(&doc.to_string()), doc);
+//! ```
+//! Text which is already in canonical form is reproduced byte for byte. In
+//! canonical form every line, including the last, ends with a line feed, a
+//! single space follows the prefix of headings, links, list items and quotes
+//! and separates a link's url from it's text, and preformatted blocks are
+//! closed. Anything else is normalized.
+//! ````
+//! use zond::gemtext::Document;
+//!
+//! let text = "\
+//! ## Section
+//! Some text,   with its spacing kept.
+//!
+//! * A list item
+//! > A quote
+//! => /about.gmi About
+//! => gopher://example.org/
+//! ```rust
+//! fn main() {}
+//! ```
+//! ";
+//! assert_eq!(Document::parse(text).to_string(), text);
+//! assert_eq!(
+//!     Document::parse("#Title\r\n=>  a.gmi \t A").to_string(),
+//!     "# Title\n=> a.gmi A\n",
+//! );
+//! ````
+//! A [`Line::Text`] which begins with the prefix of another line type, or a
+//! line of a preformatted block which begins with a fence, cannot be
+//! represented in gemtext and is read back as a different kind of line. Such
+//! lines are never produced by the parser.
+use std::fmt;
+
+/// The fence which opens and closes a preformatted block
+pub(crate) const FENCE: &str = "```";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// A single line of gemtext, or a whole preformatted block
+pub enum Line {
+    /// An ordinary line of text, kept exactly as written
+    Text(String),
+    /// A link to another document
+    Link {
+        /// The url, which may be relative
+        url: String,
+        /// The text shown in place of the url
+        display: Option<String>,
+    },
+    /// A heading
+    Heading {
+        /// The heading level, from 1 to 3
+        level: u8,
+        /// The text of the heading
+        text: String,
+    },
+    /// An unordered list item
+    ListItem(String),
+    /// A quotation
+    Quote(String),
+    /// A preformatted block, whose lines are shown exactly as written
+    Preformatted {
+        /// The text following the opening fence, used as alt text
+        alt: Option<String>,
+        /// The lines between the opening and closing fences
+        lines: Vec<String>,
+    },
+}
+
+impl Line {
+    /// Parses a single line which is not part of a preformatted block. Any
+    /// carriage returns at the end of the line are dropped.
+    /// ```
+    /// use zond::gemtext::Line;
+    ///
+    /// assert_eq!(Line::parse("* item"), Line::ListItem("item".to_string()));
+    /// assert_eq!(
+    ///     Line::parse("###heading"),
+    ///     Line::Heading { level: 3, text: "heading".to_string() },
+    /// );
+    /// assert_eq!(
+    ///     Line::parse("=> foo.gmi"),
+    ///     Line::Link { url: "foo.gmi".to_string(), display: None },
+    /// );
+    /// // A link needs a url and a list item needs a space
+    /// assert_eq!(Line::parse("=>  "), Line::Text("=>  ".to_string()));
+    /// assert_eq!(Line::parse("*item"), Line::Text("*item".to_string()));
+    /// ```
+    pub fn parse(line: &str) -> Self {
+        let line = line.trim_end_matches('\r');
+        if let Some(rest) = line.strip_prefix("=>") {
+            let rest = rest.trim();
+            if !rest.is_empty() {
+                let (url, display) = match rest.split_once(char::is_whitespace) {
+                    Some((url, display)) => (url, Some(display.trim_start().to_string())),
+                    None => (rest, None),
+                };
+                return Self::Link {
+                    url: url.to_string(),
+                    display,
+                };
+            }
+        } else if line.starts_with('#') {
+            let level = line.chars().take(3).take_while(|c| *c == '#').count();
+            return Self::Heading {
+                level: u8::try_from(level).unwrap_or(3),
+                text: line[level..].trim().to_string(),
+            };
+        } else if let Some(item) = line.strip_prefix("* ") {
+            return Self::ListItem(item.trim().to_string());
+        } else if let Some(quote) = line.strip_prefix('>') {
+            return Self::Quote(quote.trim().to_string());
+        }
+        Self::Text(line.to_string())
+    }
+
+    /// Returns the text of the line as it would be shown to a reader, without
+    /// it's prefix. Links without any text show their url, and the lines of a
+    /// preformatted block are joined with line feeds.
+    #[must_use]
+    pub fn text(&self) -> String {
+        match self {
+            Self::Text(s) | Self::Heading { text: s, .. } | Self::ListItem(s) | Self::Quote(s) => {
+                s.clone()
+            }
+            Self::Link { url, display } => display.as_ref().unwrap_or(url).clone(),
+            Self::Preformatted { lines, .. } => lines.join("\n"),
+        }
+    }
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Text(s) => writeln!(f, "{s}"),
+            Self::Link { url, display } => match display.as_deref() {
+                Some(d) if !d.is_empty() => writeln!(f, "=> {url} {d}"),
+                _ => writeln!(f, "=> {url}"),
+            },
+            Self::Heading { level, text } => {
+                let hashes = &"###"[..usize::from((*level).clamp(1, 3))];
+                if text.is_empty() {
+                    writeln!(f, "{hashes}")
+                } else {
+                    writeln!(f, "{hashes} {text}")
+                }
+            }
+            Self::ListItem(s) => writeln!(f, "* {s}"),
+            Self::Quote(s) if s.is_empty() => writeln!(f, ">"),
+            Self::Quote(s) => writeln!(f, "> {s}"),
+            Self::Preformatted { alt, lines } => {
+                writeln!(f, "{FENCE}{}", alt.as_deref().unwrap_or_default())?;
+                for line in lines {
+                    writeln!(f, "{line}")?;
+                }
+                writeln!(f, "{FENCE}")
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+/// A parsed gemtext document
+pub struct Document {
+    /// The lines of the document, in order
+    pub lines: Vec<Line>,
+}
+
+impl Document {
+    /// Parses gemtext. Every input is valid gemtext, so this cannot fail. Both
+    /// `\n` and `\r\n` line endings are accepted, any other carriage returns at
+    /// the end of a line are dropped, and a preformatted block left open at the
+    /// end of the input is closed.
+    /// ```
+    /// use zond::gemtext::{Document, Line};
+    ///
+    /// let doc = Document::parse("```sh alt\n=> not a link\n```ignored\n");
+    /// assert_eq!(
+    ///     doc.lines,
+    ///     [Line::Preformatted {
+    ///         alt: Some("sh alt".to_string()),
+    ///         lines: vec!["=> not a link".to_string()],
+    ///     }]
+    /// );
+    /// assert!(Document::parse("").lines.is_empty());
+    /// ```
+    pub fn parse(text: &str) -> Self {
+        let mut lines = vec![];
+        let mut pre: Option<(Option<String>, Vec<String>)> = None;
+        for line in text.lines() {
+            let line = line.trim_end_matches('\r');
+            match (&mut pre, line.strip_prefix(FENCE)) {
+                (Some(_), Some(_)) => {
+                    if let Some((alt, block)) = pre.take() {
+                        lines.push(Line::Preformatted { alt, lines: block });
+                    }
+                }
+                (Some((_, block)), None) => block.push(line.to_string()),
+                (None, Some(alt)) => {
+                    let alt = Some(alt.trim()).filter(|a| !a.is_empty());
+                    pre = Some((alt.map(ToString::to_string), vec![]));
+                }
+                (None, None) => lines.push(Line::parse(line)),
+            }
+        }
+        if let Some((alt, block)) = pre {
+            lines.push(Line::Preformatted { alt, lines: block });
+        }
+        Self { lines }
+    }
+
+    /// Returns the url and text of every link in the document
+    /// ```
+    /// use zond::gemtext::Document;
+    ///
+    /// let doc = Document::parse("=> a.gmi A\n```\n=> b.gmi B\n```\n=> c.gmi\n");
+    /// let links: Vec<_> = doc.links().collect();
+    /// assert_eq!(links, [("a.gmi", Some("A")), ("c.gmi", None)]);
+    /// ```
+    pub fn links(&self) -> impl Iterator<Item = (&str, Option<&str>)> {
+        self.lines.iter().filter_map(|line| match line {
+            Line::Link { url, display } => Some((url.as_str(), display.as_deref())),
+            _ => None,
+        })
+    }
+
+    /// Returns the level and text of every heading in the document
+    /// ```
+    /// use zond::gemtext::Document;
+    ///
+    /// let doc = Document::parse("# One\n```\n## Not a heading\n```\n### Two\n");
+    /// let headings: Vec<_> = doc.headings().collect();
+    /// assert_eq!(headings, [(1, "One"), (3, "Two")]);
+    /// ```
+    pub fn headings(&self) -> impl Iterator<Item = (u8, &str)> {
+        self.lines.iter().filter_map(|line| match line {
+            Line::Heading { level, text } => Some((*level, text.as_str())),
+            _ => None,
+        })
+    }
+}
+
+impl fmt::Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in &self.lines {
+            write!(f, "{line}")?;
+        }
+        Ok(())
+    }
+}
+
+impl From<&str> for Document {
+    fn from(text: &str) -> Self {
+        Self::parse(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses `text` and checks that serializing and parsing it again gives
+    /// back the same document
+    fn round_trip(text: &str) -> Document {
+        let doc = Document::parse(text);
+        assert_eq!(Document::parse(&doc.to_string()), doc, "{text:?}");
+        doc
+    }
+
+    #[test]
+    fn crlf() {
+        let doc = round_trip("# Title\r\ntext\r\n```\r\ncode\r\n```\r\n");
+        assert_eq!(doc.to_string(), "# Title\ntext\n```\ncode\n```\n");
+    }
+
+    #[test]
+    fn stray_carriage_return() {
+        let doc = round_trip("a\r\r\n=> b.gmi B\r\r\n```\r\r\nc\r\r\n");
+        assert_eq!(
+            doc.lines,
+            [
+                Line::Text("a".to_string()),
+                Line::Link {
+                    url: "b.gmi".to_string(),
+                    display: Some("B".to_string()),
+                },
+                Line::Preformatted {
+                    alt: None,
+                    lines: vec!["c".to_string()],
+                },
+            ]
+        );
+        assert_eq!(round_trip("a\r").lines, [Line::Text("a".to_string())]);
+        assert_eq!(round_trip("a\rb\n").lines, [Line::Text("a\rb".to_string())]);
+    }
+
+    #[test]
+    fn preformatted_toggles() {
+        let doc = round_trip("```rust code\nfn main() {}\n```closing alt\n```\n\n```  \n");
+        assert_eq!(
+            doc.lines,
+            [
+                Line::Preformatted {
+                    alt: Some("rust code".to_string()),
+                    lines: vec!["fn main() {}".to_string()],
+                },
+                Line::Preformatted {
+                    alt: None,
+                    lines: vec![String::new()],
+                },
+            ]
+        );
+        assert_eq!(
+            doc.to_string(),
+            "```rust code\nfn main() {}\n```\n```\n\n```\n"
+        );
+    }
+
+    #[test]
+    fn empty_links() {
+        let doc = round_trip("=>\n=> \n=>\t\n=> a.gmi \n");
+        assert_eq!(
+            doc.lines,
+            [
+                Line::Text("=>".to_string()),
+                Line::Text("=> ".to_string()),
+                Line::Text("=>\t".to_string()),
+                Line::Link {
+                    url: "a.gmi".to_string(),
+                    display: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn blank_lines() {
+        let doc = round_trip("\n\n# A\n\n\n");
+        assert_eq!(doc.to_string(), "\n\n# A\n\n\n");
+        assert_eq!(round_trip("\r\n\r\n").to_string(), "\n\n");
+        assert_eq!(round_trip(" \n").lines, [Line::Text(" ".to_string())]);
+    }
+}
@@ -5,15 +5,18 @@ use {
     config::Config,
     gettextrs::gettext,
     std::{
+        fmt::Write as _,
         fs::{self, File},
-        io::{BufReader, BufWriter, Write},
+        io::{BufReader, Write},
         path::{Path, PathBuf},
         process,
-        sync::OnceLock,
+        sync::{PoisonError, RwLock},
     },
     xml::{EmitterConfig, EventReader},
 };
 
+/// Processing of files copied from the content directory
+pub(crate) mod asset;
 /// Adds an ascii banner to each page (if the file banner.txt exists)
 pub(crate) mod banner;
 /// Generates the command line options struct
@@ -26,20 +29,35 @@ pub(crate) mod config;
 pub(crate) mod content;
 /// Zond errors
 pub mod error;
+/// Parsing and serializing gemtext
+pub mod gemtext;
 /// A Link
 pub(crate) mod link;
 /// A gemlog post
 pub(crate) mod post;
+/// Shortcode expansion within page content
+pub(crate) mod shortcode;
+/// Text written into the generated capsule
+pub(crate) mod strings;
+/// Page templates
+pub(crate) mod template;
 /// An optional tinylog
 pub(crate) mod tinylog;
 
 pub use {content::edit, error::Error};
 
-static CFG: OnceLock<Config> = OnceLock::new();
+/// The capsule configuration. Each time the configuration is reloaded the new
+/// value is leaked, so that references handed out earlier remain valid. This
+/// only happens when `Config.ron` changes while watching for changes.
+static CFG: RwLock<Option<&'static Config>> = RwLock::new(None);
 
 pub fn load_config() -> &'static Config {
-    CFG.get_or_init(|| match Config::load() {
-        Ok(c) => c,
+    if let Some(cfg) = *CFG.read().unwrap_or_else(PoisonError::into_inner) {
+        return cfg;
+    }
+    let mut lock = CFG.write().unwrap_or_else(PoisonError::into_inner);
+    lock.get_or_insert_with(|| match Config::load() {
+        Ok(c) => Box::leak(Box::new(c)),
         Err(e) => {
             eprintln!("Error loading config: {e}");
             process::exit(1);
@@ -47,6 +65,16 @@ pub fn load_config() -> &'static Config {
     })
 }
 
+/// Reads `Config.ron` from disk again, replacing the cached configuration
+/// # Errors
+/// Returns an error if the file cannot be read or decoded, in which case the
+/// previous configuration remains in use
+pub fn reload_config() -> Result<(), Error> {
+    let cfg = Config::load()?;
+    *CFG.write().unwrap_or_else(PoisonError::into_inner) = Some(Box::leak(Box::new(cfg)));
+    Ok(())
+}
+
 /// Saves a content type to disk
 pub trait ToDisk {
     type Err;
@@ -109,36 +137,40 @@ impl ToDisk for Feed {
 }
 
 impl GetPath for Feed {
-    fn get_path(root: &Path, _subdir: Option<&Path>) -> PathBuf {
+    fn get_path(root: &Path, subdir: Option<&Path>) -> PathBuf {
         let mut path = root.to_path_buf();
-        path.push("gemlog");
+        path.push(subdir.unwrap_or(Path::new(config::GEMLOG)));
         path.push("atom.xml");
         path
     }
 }
 
-/// Writes the footer for each page
+/// Renders the footer for each page
 /// # Errors
 /// Returns `fmt::Error` if formatting fails
-/// Returns `io::Error` if writing to disk fails
-pub fn write_footer(writer: &mut BufWriter<File>, year: i32) -> Result<(), crate::Error> {
-    writeln!(writer)?;
+pub fn footer(year: i32, links: &[link::Link]) -> Result<String, crate::Error> {
+    let mut footer = String::new();
     let cfg = load_config();
     if let Some(license) = &cfg.license {
         writeln!(
-            writer,
+            footer,
             "{} {license}.",
-            gettext("All content for this site is licensed as")
+            strings::gettext("All content for this site is licensed as")
         )?;
     }
-    writeln!(writer, "© {year} {} {}", gettext("by"), cfg.author.name,)?;
-    for link in &cfg.footer_links {
-        writeln!(writer, "{link}")?;
+    writeln!(
+        footer,
+        "© {year} {} {}",
+        strings::gettext("by"),
+        cfg.author.name
+    )?;
+    for link in links {
+        writeln!(footer, "{link}")?;
     }
     if cfg.show_email {
         if let Some(ref email) = cfg.author.email {
-            writeln!(writer, "=> mailto:{email} {}", gettext("Contact"))?;
+            writeln!(footer, "=> mailto:{email} {}", strings::gettext("Contact"))?;
         }
     }
-    Ok(())
+    Ok(footer)
 }
@@ -1,7 +1,7 @@
 use {
     crate::content::Meta,
     serde::{Deserialize, Serialize},
-    std::{env, fmt, path::Path},
+    std::{fmt, path::Path},
 };
 
 #[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
@@ -19,14 +19,11 @@ impl fmt::Display for Link {
 }
 
 impl Link {
-    /// Takes the original path in the content tree and meta information and
-    /// returns a `Link` struct
-    pub fn get(origin: &Path, meta: &Meta) -> Result<Self, crate::Error> {
+    /// Takes the path of a page within the generated capsule and it's meta
+    /// information and returns a `Link` struct
+    pub fn get(path: &Path, meta: &Meta) -> Result<Self, crate::Error> {
         let cfg = crate::load_config();
         let mut url = cfg.url()?;
-        let mut current = env::current_dir()?;
-        current.push("content");
-        let path = origin.strip_prefix(current)?;
         url.set_path(&path.to_string_lossy());
         Ok(Self {
             url: url.to_string(),
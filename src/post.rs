@@ -1,10 +1,10 @@
 use {
     crate::{
-        content::{Categories, Meta},
+        content::{stats, Categories, Meta},
         link::Link,
+        strings,
     },
     atom_syndication as atom,
-    gettextrs::gettext,
     tinylog::Time as _,
 };
 
@@ -12,6 +12,12 @@ use {
 pub(crate) struct Post {
     pub meta: Meta,
     pub link: Link,
+    /// The number of words in the post
+    pub words: usize,
+    /// The language of the post, if not the default language of the capsule
+    pub lang: Option<String>,
+    /// The collection the post is an entry in
+    pub collection: String,
 }
 
 impl TryFrom<&Post> for atom::Entry {
@@ -24,21 +30,32 @@ impl TryFrom<&Post> for atom::Entry {
         link.set_href(&post.link.url);
         link.set_rel("alternate");
         let author = cfg.author.to_atom();
+        let summary = match (&post.meta.summary, stats::line(post.words)) {
+            (Some(text), Some(stats)) => Some(format!("{text} ({stats})")),
+            (Some(text), None) => Some(text.clone()),
+            (None, stats) => stats,
+        };
+        // Entries have no language of their own, so it is set on each text
+        // construct instead
+        let text = |s: String| atom::Text {
+            lang: post.lang.clone(),
+            ..atom::Text::plain(s)
+        };
         let entry = atom::EntryBuilder::default()
-            .title(post.meta.title.clone())
-            .id(&post.link.url)
+            .title(text(post.meta.title.clone()))
+            .id(post.meta.id.as_deref().unwrap_or(&post.link.url))
             .updated(post.meta.published.as_ref().unwrap().to_date_time()?)
             .authors(vec![author])
             .categories(Categories::try_from(&post.meta)?)
             .link(link)
             .published(post.meta.published.as_ref().unwrap().to_date_time()?)
-            .rights(atom::Text::plain(format!(
+            .rights(text(format!(
                 "© {} {} {}",
                 post.meta.published.as_ref().unwrap().year(),
-                gettext("by"),
+                strings::gettext("by"),
                 &cfg.author.name
             )))
-            .summary(post.meta.summary.as_ref().map(atom::Text::plain))
+            .summary(summary.map(text))
             .build();
         Ok(entry)
     }
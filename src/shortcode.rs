@@ -0,0 +1,187 @@
+use {
+    crate::gemtext::FENCE,
+    gettextrs::gettext,
+    std::{collections::HashMap, path::Path},
+};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+/// A single `{% name key=value "positional" %}` shortcode
+pub struct Shortcode {
+    /// The name of the shortcode
+    pub name: String,
+    /// Arguments given as `key=value` pairs
+    pub args: HashMap<String, String>,
+    /// Arguments given without a key
+    pub positional: Vec<String>,
+}
+
+impl Shortcode {
+    /// Parses the text found between the `{%` and `%}` delimiters
+    pub fn parse(s: &str) -> Self {
+        let mut words = split(s).into_iter();
+        let mut code = Self {
+            name: words.next().unwrap_or_default(),
+            ..Default::default()
+        };
+        for word in words {
+            match word.split_once('=') {
+                Some((key, val)) => {
+                    code.args
+                        .insert(key.to_string(), val.trim_matches('"').to_string());
+                }
+                None => code.positional.push(word),
+            }
+        }
+        code
+    }
+
+    /// Returns the value of the named argument
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.args.get(key).map(String::as_str)
+    }
+
+    /// Returns the value of the named argument as a number
+    /// # Errors
+    /// Returns an error if the argument is present but is not a number
+    pub fn get_usize(&self, key: &str) -> Result<Option<usize>, crate::Error> {
+        match self.get(key) {
+            Some(val) => match val.parse() {
+                Ok(n) => Ok(Some(n)),
+                Err(_) => Err(format!(
+                    "{} `{key}` {}: {val}",
+                    gettext("Argument"),
+                    gettext("is not a number")
+                )
+                .into()),
+            },
+            None => Ok(None),
+        }
+    }
+}
+
+/// Splits the shortcode body on whitespace, keeping quoted strings together
+fn split(s: &str) -> Vec<String> {
+    let mut words = vec![];
+    let mut word = String::new();
+    let mut quoted = false;
+    for c in s.trim().chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                if word.contains('=') {
+                    word.push(c);
+                } else if !quoted {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            c if c.is_whitespace() && !quoted => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            c => word.push(c),
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// A type which can supply the replacement text for a shortcode
+pub trait Expand {
+    /// Returns the text which replaces `code` in the page at `source`, or `None`
+    /// if the shortcode is not recognized
+    /// # Errors
+    /// Returns an error if the shortcode is recognized but cannot be expanded
+    fn expand(&self, code: &Shortcode, source: &Path) -> Result<Option<String>, crate::Error>;
+}
+
+/// Replaces every shortcode in `content`. Unrecognized shortcodes are left in
+/// place and a warning is printed. Preformatted blocks are copied unchanged, so
+/// that they can show shortcodes without them being expanded.
+/// # Errors
+/// Errors are bubbled up from the expander
+pub fn render(content: &str, source: &Path, ctx: &impl Expand) -> Result<String, crate::Error> {
+    let mut out = String::with_capacity(content.len());
+    let mut text = String::new();
+    let mut pre = false;
+    for line in content.split_inclusive('\n') {
+        if line.starts_with(FENCE) {
+            if !pre {
+                out.push_str(&render_text(&text, source, ctx)?);
+                text.clear();
+            }
+            pre = !pre;
+            out.push_str(line);
+        } else if pre {
+            out.push_str(line);
+        } else {
+            text.push_str(line);
+        }
+    }
+    out.push_str(&render_text(&text, source, ctx)?);
+    Ok(out)
+}
+
+/// Replaces every shortcode in a run of text outside of preformatted blocks
+fn render_text(content: &str, source: &Path, ctx: &impl Expand) -> Result<String, crate::Error> {
+    let mut out = String::with_capacity(content.len());
+    let mut rest = content;
+    while let Some(start) = rest.find("{%") {
+        out.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find("%}") else {
+            rest = &rest[start..];
+            break;
+        };
+        let raw = &rest[start..start + end + 2];
+        let code = Shortcode::parse(&rest[start + 2..start + end]);
+        if let Some(s) = ctx.expand(&code, source)? {
+            out.push_str(&s);
+        } else {
+            eprintln!(
+                "{}: {}: {raw}",
+                source.display(),
+                gettext("Unknown shortcode")
+            );
+            out.push_str(raw);
+        }
+        rest = &rest[start + end + 2..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Expands `{% name %}` to the shortcode's name in capitals
+    struct Upper;
+
+    impl Expand for Upper {
+        fn expand(&self, code: &Shortcode, _: &Path) -> Result<Option<String>, crate::Error> {
+            Ok(Some(code.name.to_uppercase()))
+        }
+    }
+
+    fn render(content: &str) -> String {
+        super::render(content, Path::new("page.gmi"), &Upper).unwrap()
+    }
+
+    #[test]
+    fn expands_text() {
+        assert_eq!(render("a {% b %} c\n{%\nd\n%}"), "a B c\nD");
+    }
+
+    #[test]
+    fn preformatted_left_alone() {
+        let text = "{% a %}\n```gemtext\n{% b %}\r\n```\n{% c %}\n";
+        assert_eq!(render(text), "A\n```gemtext\n{% b %}\r\n```\nC\n");
+    }
+
+    #[test]
+    fn unclosed_block() {
+        assert_eq!(render("{% a %}\n```\n{% b %}"), "A\n```\n{% b %}");
+    }
+}
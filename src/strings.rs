@@ -0,0 +1,102 @@
+use std::{collections::HashMap, sync::OnceLock};
+
+/// The name of the directory holding the generated tag pages. Unlike the link
+/// text pointing to it, this is never translated so that urls stay the same
+/// regardless of the language of the capsule.
+pub const TAGS_DIR: &str = "tags";
+
+/// The translations shipped with zond, as `(language, po file)` pairs
+const CATALOGS: &[(&str, &str)] = &[("it", include_str!("../po/it.po"))];
+
+static PARSED: OnceLock<HashMap<&'static str, HashMap<String, String>>> = OnceLock::new();
+
+/// Returns the translation of `msgid` to be written into the generated capsule.
+///
+/// Unlike [`gettextrs::gettext`], which follows the locale of whoever is
+/// running zond, this uses the `language` set in `Config.ron`, falling back to
+/// the capsule's `lang`. A string set in the `strings` table of `Config.ron`
+/// takes precedence over any translation. If no translation is found the
+/// original English string is returned.
+pub fn gettext(msgid: &str) -> String {
+    let cfg = crate::load_config();
+    if let Some(s) = cfg.strings.get(msgid) {
+        return s.clone();
+    }
+    let Some(lang) = cfg.language.as_deref().or(cfg.lang.as_deref()) else {
+        return msgid.to_string();
+    };
+    let catalogs = PARSED.get_or_init(|| {
+        CATALOGS
+            .iter()
+            .map(|(lang, po)| (*lang, parse(po)))
+            .collect()
+    });
+    let primary = lang.split(['-', '_']).next().unwrap_or(lang);
+    catalogs
+        .get(lang)
+        .or_else(|| catalogs.get(primary))
+        .and_then(|c| c.get(msgid))
+        .cloned()
+        .unwrap_or_else(|| msgid.to_string())
+}
+
+/// Reads the `msgid` and `msgstr` pairs from a po file. Fuzzy and untranslated
+/// entries are skipped.
+fn parse(po: &str) -> HashMap<String, String> {
+    let mut map = HashMap::new();
+    let mut fuzzy = false;
+    let mut id: Option<String> = None;
+    let mut msg: Option<String> = None;
+    let mut finish = |id: &mut Option<String>, msg: &mut Option<String>, fuzzy: bool| {
+        if let (Some(i), Some(m)) = (id.take(), msg.take()) {
+            if !fuzzy && !i.is_empty() && !m.is_empty() {
+                map.insert(i, m);
+            }
+        }
+    };
+    for line in po.lines().map(str::trim) {
+        if line.is_empty() {
+            finish(&mut id, &mut msg, fuzzy);
+            fuzzy = false;
+        } else if line.starts_with("#,") {
+            fuzzy = line.contains("fuzzy");
+        } else if let Some(s) = line.strip_prefix("msgid ") {
+            finish(&mut id, &mut msg, fuzzy);
+            id = Some(unquote(s));
+        } else if let Some(s) = line.strip_prefix("msgstr ") {
+            msg = Some(unquote(s));
+        } else if line.starts_with('"') {
+            if let Some(m) = msg.as_mut() {
+                m.push_str(&unquote(line));
+            } else if let Some(i) = id.as_mut() {
+                i.push_str(&unquote(line));
+            }
+        }
+    }
+    finish(&mut id, &mut msg, fuzzy);
+    map
+}
+
+/// Removes the surrounding quotes from a po file string and processes escapes
+fn unquote(s: &str) -> String {
+    let s = s.trim();
+    let s = s
+        .strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .unwrap_or(s);
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') => out.push('\n'),
+                Some('t') => out.push('\t'),
+                Some(c) => out.push(c),
+                None => {}
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
@@ -0,0 +1,167 @@
+use {
+    gettextrs::gettext,
+    serde::{Deserialize, Serialize},
+    std::{fs, path::PathBuf},
+};
+
+/// The built in layout used for pages and gemlog posts
+const PAGE: &str = "{{ banner }}# {{ title }}
+{{ date }}{{ content }}
+
+{{ tags }}{{ translations }}{{ nav }}
+{{ footer }}";
+
+/// The built in layout used for generated tag pages and indexes
+const INDEX: &str = "{{ banner }}# {{ title }}
+
+{{ content }}
+{{ translations }}{{ nav }}
+{{ footer }}";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// The type of document being rendered, used to select a default template
+pub enum Kind {
+    /// An ordinary page
+    Page,
+    /// A gemlog post
+    Post,
+    /// A generated tag page or the tag index
+    Tags,
+    /// The main or gemlog index
+    Index,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default)]
+/// The names of the default templates for each kind of document. A name refers
+/// to the file `templates/<name>.gmi` in the capsule root.
+pub struct Templates {
+    /// The template used for ordinary pages
+    pub page: String,
+    /// The template used for gemlog posts
+    pub post: String,
+    /// The template used for tag pages and the tag index
+    pub tags: String,
+    /// The template used for the main and gemlog indexes
+    pub index: String,
+}
+
+impl Default for Templates {
+    fn default() -> Self {
+        Self {
+            page: "page".to_string(),
+            post: "post".to_string(),
+            tags: "tags".to_string(),
+            index: "index".to_string(),
+        }
+    }
+}
+
+impl Templates {
+    /// Returns the name of the configured template for this kind of document
+    pub fn name(&self, kind: Kind) -> &str {
+        match kind {
+            Kind::Page => &self.page,
+            Kind::Post => &self.post,
+            Kind::Tags => &self.tags,
+            Kind::Index => &self.index,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+/// The values which are substituted for the placeholders in a template
+pub struct Vars {
+    /// The ascii art banner, including the preformatted block fences
+    pub banner: String,
+    /// The page title
+    pub title: String,
+    /// The date heading
+    pub date: String,
+    /// The body of the page
+    pub content: String,
+    /// The list of tags for this page
+    pub tags: String,
+    /// Links to translations of this page
+    pub translations: String,
+    /// Navigation links
+    pub nav: String,
+    /// License, copyright and footer links
+    pub footer: String,
+}
+
+impl Vars {
+    fn get(&self, key: &str) -> Option<&str> {
+        match key {
+            "banner" => Some(&self.banner),
+            "title" => Some(&self.title),
+            "date" => Some(&self.date),
+            "content" => Some(&self.content),
+            "tags" => Some(&self.tags),
+            "translations" => Some(&self.translations),
+            "nav" => Some(&self.nav),
+            "footer" => Some(&self.footer),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+/// A page layout with `{{ placeholder }}` markers
+pub struct Template(
+    /// The raw text of the template
+    pub String,
+);
+
+impl Template {
+    /// Returns the built in template for this kind of document
+    pub fn builtin(kind: Kind) -> Self {
+        match kind {
+            Kind::Page | Kind::Post => Self(PAGE.to_string()),
+            Kind::Tags | Kind::Index => Self(INDEX.to_string()),
+        }
+    }
+
+    /// Loads a template by name from the `templates` directory. If `name` is
+    /// `None`, the configured default for `kind` is used, falling back to the
+    /// built in template if that file does not exist.
+    /// # Errors
+    /// Returns an error if a template was requested explicitly but cannot be read
+    pub fn get(name: Option<&str>, kind: Kind) -> Result<Self, crate::Error> {
+        let cfg = crate::load_config();
+        let (name, explicit) = match name {
+            Some(n) => (n, true),
+            None => (cfg.templates.name(kind), false),
+        };
+        let path: PathBuf = ["templates", &format!("{name}.gmi")].iter().collect();
+        if path.exists() {
+            Ok(Self(fs::read_to_string(&path)?))
+        } else if explicit {
+            Err(format!("{}: {}", gettext("Missing template"), path.display()).into())
+        } else {
+            Ok(Self::builtin(kind))
+        }
+    }
+
+    /// Substitutes every known placeholder with it's value. Unknown placeholders
+    /// are left in place.
+    pub fn render(&self, vars: &Vars) -> String {
+        let mut out = String::with_capacity(self.0.len());
+        let mut rest = self.0.as_str();
+        while let Some(start) = rest.find("{{") {
+            out.push_str(&rest[..start]);
+            let Some(end) = rest[start..].find("}}") else {
+                rest = &rest[start..];
+                break;
+            };
+            let key = rest[start + 2..start + end].trim();
+            match vars.get(key) {
+                Some(val) => out.push_str(val),
+                None => out.push_str(&rest[start..start + end + 2]),
+            }
+            rest = &rest[start + end + 2..];
+        }
+        out.push_str(rest);
+        out
+    }
+}
@@ -41,6 +41,7 @@ pub fn init(
         let _path = Page::create(
             Kind::Page(Some(path)),
             if let Some(t) = title { t } else { "Tinylog" },
+            None,
             summary,
             tags.unwrap_or(vec![])
                 .iter()